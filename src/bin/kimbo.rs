@@ -1,5 +1,10 @@
-use kimbo::io::main_loop;
+use kimbo::io::{cli::run_cli, main_loop};
+use std::process;
 
 fn main() {
-    main_loop()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        main_loop()
+    }
+    process::exit(run_cli(&args))
 }
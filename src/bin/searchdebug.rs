@@ -1,10 +1,8 @@
-use kimbo::io::outputs::display_board;
-use kimbo::position::zobrist::ZobristVals;
+use kimbo::io::outputs::{display_board, UciObserver};
+use kimbo::io::SearchStats;
 use kimbo::position::Position;
+use kimbo::search::builder::SearchLimits;
 use kimbo::search::Engine;
-use kimbo::tables::pawn::PawnHashTable;
-use kimbo::tables::search::HashTable;
-use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -30,26 +28,24 @@ pub const _POSITIONS: [&str; 12] = [
 
 fn _search_all() {
     // params
-    let max_time = 1000;
-    let max_depth = i8::MAX;
-    let tt = Arc::new(HashTable::new(32 * 1024 * 1024));
-    let pt = Arc::new(PawnHashTable::new(1024 * 1024));
-    let zvals = Arc::new(ZobristVals::default());
+    let limits = SearchLimits {
+        move_time: Some(1000),
+        ..Default::default()
+    };
+    let mut engine = Engine::builder()
+        .hash_size(32)
+        .move_overhead(0)
+        .observer(Arc::new(UciObserver::default()))
+        .build();
+    let zvals = engine.zobrist_vals();
     let now = Instant::now();
     for pos in _POSITIONS {
-        let position = Position::from_fen(pos, zvals.clone()).unwrap();
-        let mut search = Engine::new(
-            position,
-            Arc::new(AtomicBool::new(false)),
-            max_time,
-            max_depth,
-            u64::MAX,
-            tt.clone(),
-            pt.clone(),
-        );
-        assert_eq!(String::from(pos), search.board.to_fen());
+        engine.set_position(Position::from_fen(pos, zvals.clone()).unwrap());
+        assert_eq!(String::from(pos), engine.board.to_fen());
         println!("fen: {}", pos);
-        search.go::<true>();
+        let result = engine.search(&limits);
+        SearchStats::from(&result).report();
+        println!("stop reason {:?}", result.stop_reason);
         println!(" ");
     }
     println!("Total time: {}ms", now.elapsed().as_millis());
@@ -57,24 +53,20 @@ fn _search_all() {
 
 fn _search_one(pos: usize) {
     // params
-    let max_time = 5000;
-    let max_depth = i8::MAX;
-    let tt = Arc::new(HashTable::new(32 * 1024 * 1024));
-    let pt = Arc::new(PawnHashTable::new(1024 * 1024));
-    let zvals = Arc::new(ZobristVals::default());
-    let position = Position::from_fen(_POSITIONS[pos], zvals).unwrap();
-    let mut search = Engine::new(
-        position,
-        Arc::new(AtomicBool::new(false)),
-        max_time,
-        max_depth,
-        u64::MAX,
-        tt,
-        pt,
-    );
-    display_board(&search.board);
+    let limits = SearchLimits {
+        move_time: Some(5000),
+        ..Default::default()
+    };
+    let mut engine = Engine::builder()
+        .hash_size(32)
+        .move_overhead(0)
+        .observer(Arc::new(UciObserver::default()))
+        .build();
+    let position = Position::from_fen(_POSITIONS[pos], engine.zobrist_vals()).unwrap();
+    engine.set_position(position);
+    display_board(&engine.board);
     println!("fen: {}", _POSITIONS[pos]);
-    search.go::<true>();
+    engine.search(&limits);
 }
 
 fn main() {
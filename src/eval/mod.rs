@@ -1,5 +1,7 @@
+pub mod trace;
 pub mod tuner;
 pub mod tuner_eval;
+pub mod wdl;
 
 use crate::position::consts::*;
 use crate::position::*;
@@ -55,11 +57,25 @@ pub fn calc_pst<const MG: bool>(pos: &Position) -> [i16; 2] {
 
 const SIDE_FACTOR: [i16; 3] = [1, -1, 0];
 
+/// Unweighted pawn structure terms for one side
+struct PawnTerms {
+    doubled: i16,
+    isolated: i16,
+    passed: i16,
+    pawn_shield: i16,
+    open_files: i16,
+}
+
 #[inline(always)]
 pub const fn taper(phase: i32, mg: i16, eg: i16) -> i16 {
     ((phase * mg as i32 + (TOTALPHASE - phase) * eg as i32) / TOTALPHASE) as i16
 }
 
+#[inline(always)]
+pub fn taper_f64(phase: i32, mg: f64, eg: f64) -> f64 {
+    (phase as f64 * mg + (TOTALPHASE - phase) as f64 * eg) / TOTALPHASE as f64
+}
+
 #[inline(always)]
 fn eval_factor(phase: i32, mg: [i16; 2], eg: [i16; 2]) -> i16 {
     let eval_mg = mg[0] - mg[1];
@@ -118,6 +134,22 @@ impl Position {
     }
 
     fn side_pawn_score(&self, side: usize) -> [i16; 2] {
+        let terms = self.side_pawn_terms(side);
+        let mg = terms.doubled * DOUBLED_MG
+            + terms.isolated * ISOLATED_MG
+            + terms.passed * PASSED_MG
+            + terms.pawn_shield * PAWN_SHIELD_MG
+            + terms.open_files * PAWN_OPEN_FILE_MG;
+        let eg = terms.doubled * DOUBLED_EG
+            + terms.isolated * ISOLATED_EG
+            + terms.passed * PASSED_EG
+            + terms.pawn_shield * PAWN_SHIELD_EG
+            + terms.open_files * PAWN_OPEN_FILE_EG;
+        [mg, eg]
+    }
+
+    /// counts of each pawn structure term, before weighting
+    fn side_pawn_terms(&self, side: usize) -> PawnTerms {
         let mut doubled = 0;
         let mut isolated = 0;
         let mut passed = 0;
@@ -138,24 +170,19 @@ impl Position {
         // a *very* primitive king safety eval
         let king_idx = ls1b_scan(self.pieces[side][Piece::KING]);
         let king_file = (king_idx & 7) as i8;
-        let protecting_pawns =
+        let pawn_shield =
             (KING_ATTACKS[king_idx as usize] & self.pieces[side][Piece::PAWN]).count_ones() as i16;
         let mut open_files = 0;
         for file in std::cmp::max(0, king_file - 1)..=std::cmp::min(7, king_file + 1) {
             open_files += (FILES[file as usize] & self.pieces[side][Piece::PAWN] == 0) as i16
         }
-        // score
-        let mg = doubled * DOUBLED_MG
-            + isolated * ISOLATED_MG
-            + passed * PASSED_MG
-            + protecting_pawns * PAWN_SHIELD_MG
-            + open_files * PAWN_OPEN_FILE_MG;
-        let eg = doubled * DOUBLED_EG
-            + isolated * ISOLATED_EG
-            + passed * PASSED_EG
-            + protecting_pawns * PAWN_SHIELD_EG
-            + open_files * PAWN_OPEN_FILE_EG;
-        [mg, eg]
+        PawnTerms {
+            doubled,
+            isolated,
+            passed,
+            pawn_shield,
+            open_files,
+        }
     }
 
     fn eg_king_score(&self, winning_side: usize, phase: i32) -> i16 {
@@ -0,0 +1,88 @@
+use super::{eval_factor, taper, PawnTerms, SIDE_FACTOR};
+use crate::position::consts::*;
+use crate::position::Position;
+
+/// [midgame, endgame] scores for each side, white first
+pub type SideScores = [[i16; 2]; 2];
+
+/// Every term of the static evaluation, before tapering
+pub struct EvalTrace {
+    pub material: SideScores,
+    pub pst: SideScores,
+    pub doubled: SideScores,
+    pub isolated: SideScores,
+    pub passed: SideScores,
+    pub pawn_shield: SideScores,
+    pub open_files: SideScores,
+    /// Mop-up for the winning side's king, endgame only
+    pub king_mopup: SideScores,
+    /// Capped at TOTALPHASE
+    pub phase: i32,
+    /// Final eval from white's point of view, as given by static_eval
+    pub eval: i16,
+}
+
+impl EvalTrace {
+    /// terms in display order
+    pub fn terms(&self) -> [(&'static str, SideScores); 8] {
+        [
+            ("Material", self.material),
+            ("PST", self.pst),
+            ("Doubled", self.doubled),
+            ("Isolated", self.isolated),
+            ("Passed", self.passed),
+            ("Pawn shield", self.pawn_shield),
+            ("Open files", self.open_files),
+            ("King mop-up", self.king_mopup),
+        ]
+    }
+
+    /// contribution of a term to the eval, from white's point of view
+    pub fn tapered(&self, term: SideScores) -> i16 {
+        taper(self.phase, term[0][0] - term[1][0], term[0][1] - term[1][1])
+    }
+}
+
+fn weighted(terms: &[PawnTerms; 2], count: fn(&PawnTerms) -> i16, mg: i16, eg: i16) -> SideScores {
+    [0, 1].map(|side| [count(&terms[side]) * mg, count(&terms[side]) * eg])
+}
+
+impl Position {
+    /// static evaluation split into its terms, bypassing the pawn hash table
+    pub fn eval_trace(&self) -> EvalTrace {
+        let phase = std::cmp::min(self.phase as i32, TOTALPHASE);
+        let pawns = [self.side_pawn_terms(0), self.side_pawn_terms(1)];
+
+        // final eval, exactly as in static_eval
+        let wp = self.side_pawn_score(0);
+        let bp = self.side_pawn_score(1);
+        let mut eval = eval_factor(phase, self.mat_mg, self.mat_eg)
+            + eval_factor(phase, self.pst_mg, self.pst_eg)
+            + taper(phase, wp[0] - bp[0], wp[1] - bp[1]);
+        let mut king_mopup = [[0; 2]; 2];
+        if eval != 0 {
+            let winning_side = (eval < 0) as usize;
+            king_mopup[winning_side][1] =
+                SIDE_FACTOR[winning_side] * self.eg_king_score(winning_side, 0);
+            eval += self.eg_king_score(winning_side, phase);
+        }
+
+        EvalTrace {
+            material: [0, 1].map(|side| [self.mat_mg[side], self.mat_eg[side]]),
+            pst: [0, 1].map(|side| [self.pst_mg[side], self.pst_eg[side]]),
+            doubled: weighted(&pawns, |t| t.doubled, DOUBLED_MG, DOUBLED_EG),
+            isolated: weighted(&pawns, |t| t.isolated, ISOLATED_MG, ISOLATED_EG),
+            passed: weighted(&pawns, |t| t.passed, PASSED_MG, PASSED_EG),
+            pawn_shield: weighted(&pawns, |t| t.pawn_shield, PAWN_SHIELD_MG, PAWN_SHIELD_EG),
+            open_files: weighted(
+                &pawns,
+                |t| t.open_files,
+                PAWN_OPEN_FILE_MG,
+                PAWN_OPEN_FILE_EG,
+            ),
+            king_mopup,
+            phase,
+            eval,
+        }
+    }
+}
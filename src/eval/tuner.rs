@@ -6,6 +6,7 @@ use std::time::Instant;
 use std::{io::BufReader, sync::Arc};
 
 use super::tuner_eval::{tuner_eval, tuner_pawn_score, ParamContainer};
+use super::wdl::WdlParams;
 
 #[derive(Debug)]
 pub struct TunerPosition {
@@ -69,7 +70,7 @@ fn get_positions(filename: &str) -> Vec<TunerPosition> {
     positions
 }
 
-fn sigmoid(k: f64, x: f64) -> f64 {
+pub fn sigmoid(k: f64, x: f64) -> f64 {
     1.0 / (1.0 + 10f64.powf(-k * x))
 }
 
@@ -170,3 +171,84 @@ pub fn optimise<const PRINT_PARAMS: bool>(
     println!("Finished optimisation.");
     best_params
 }
+
+/// parameters of the current (hand-tuned) pawn eval, for evaluating tuner positions
+fn current_params() -> [i16; 10] {
+    [
+        DOUBLED_MG,
+        DOUBLED_EG,
+        ISOLATED_MG,
+        ISOLATED_EG,
+        PASSED_MG,
+        PASSED_EG,
+        PAWN_SHIELD_MG,
+        PAWN_SHIELD_EG,
+        PAWN_OPEN_FILE_MG,
+        PAWN_OPEN_FILE_EG,
+    ]
+}
+
+fn calculate_wdl_error(positions: &[TunerPosition], evals: &[f64], params: [f64; 4]) -> f64 {
+    let model = WdlParams::from(params);
+    let mut error = 0.0;
+    for (pos, &eval) in positions.iter().zip(evals) {
+        let predicted = model.probabilities(eval, pos.phase);
+        let actual = [
+            (pos.result == 1.0) as u8 as f64,
+            (pos.result == 0.5) as u8 as f64,
+            (pos.result == 0.0) as u8 as f64,
+        ];
+        for i in 0..3 {
+            error += (actual[i] - predicted[i]).powi(2);
+        }
+    }
+    error / positions.len() as f64
+}
+
+/// fits the win/draw/loss model to game results, same local search as the Texel tuner
+pub fn optimise_wdl(filename: &str, mut best_params: WdlParams) -> WdlParams {
+    let positions = get_positions(filename);
+    if positions.is_empty() {
+        return best_params;
+    }
+    let eval_params = current_params();
+    let evals: Vec<f64> = positions
+        .iter()
+        .map(|pos| tuner_eval(pos, &eval_params) as f64)
+        .collect();
+
+    let steps = [0.01, 0.01, 1.0, 1.0];
+    let mut params: [f64; 4] = best_params.into();
+    let mut best_error = calculate_wdl_error(&positions, &evals, params);
+    println!("Initial error: {}", best_error);
+
+    let mut improved = true;
+    let mut count = 1;
+    while improved {
+        let runtime = Instant::now();
+        improved = false;
+        for i in 0..4 {
+            for step in [steps[i], -steps[i]] {
+                let mut new_params = params;
+                new_params[i] += step;
+                let new_error = calculate_wdl_error(&positions, &evals, new_params);
+                if new_error < best_error {
+                    best_error = new_error;
+                    params = new_params;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        println!(
+            "Run {} in {}ms, error: {}",
+            count,
+            runtime.elapsed().as_millis(),
+            best_error
+        );
+        count += 1;
+    }
+    best_params = params.into();
+    println!("Finished optimisation.");
+    best_params
+}
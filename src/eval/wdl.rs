@@ -0,0 +1,76 @@
+// Win/draw/loss model
+// the win (loss) probability is a sigmoid of the score minus (plus) a draw margin,
+// with the draw probability being whatever is left over
+// both the sigmoid scale and the draw margin are tapered by phase,
+// and are fitted to game results by tuner::optimise_wdl
+use super::taper_f64;
+use super::tuner::sigmoid;
+use crate::position::consts::TOTALPHASE;
+use crate::search::is_mate_score;
+
+#[derive(Copy, Clone, Debug)]
+pub struct WdlParams {
+    pub scale_mg: f64,
+    pub scale_eg: f64,
+    pub margin_mg: f64,
+    pub margin_eg: f64,
+}
+
+/// Output of `kimbo tune <positions> wdl`, started from scale 0.30/0.40 and margin 150/200.
+/// The games came from `kimbo match --engine1 name=a,nodes=5000 --engine2 name=b,nodes=5000
+/// --tc 60+1 --openings <book> --pairs 1500 --pgn <games>`, which replays the same games for
+/// the same book. The book was 1500 openings of 8 random plies from the start position, kept
+/// if a depth 5 search scored them within 150cp. The 196858 positions are every position
+/// from ply 16 that is not in check and whose next move is not a capture or promotion,
+/// written `<fen> c9 "<result>";`.
+pub const WDL_PARAMS: WdlParams = WdlParams {
+    scale_mg: 0.20,
+    scale_eg: 0.25,
+    margin_mg: 46.0,
+    margin_eg: 218.0,
+};
+
+impl From<[f64; 4]> for WdlParams {
+    fn from(x: [f64; 4]) -> Self {
+        Self {
+            scale_mg: x[0],
+            scale_eg: x[1],
+            margin_mg: x[2],
+            margin_eg: x[3],
+        }
+    }
+}
+
+impl From<WdlParams> for [f64; 4] {
+    fn from(x: WdlParams) -> Self {
+        [x.scale_mg, x.scale_eg, x.margin_mg, x.margin_eg]
+    }
+}
+
+impl WdlParams {
+    /// win, draw and loss probabilities for a centipawn score
+    pub fn probabilities(&self, score: f64, phase: i16) -> [f64; 3] {
+        let phase = std::cmp::min(phase as i32, TOTALPHASE);
+        let k = taper_f64(phase, self.scale_mg, self.scale_eg);
+        let margin = taper_f64(phase, self.margin_mg, self.margin_eg);
+        let win = sigmoid(k, (score - margin) / 100.0);
+        let loss = sigmoid(k, (-score - margin) / 100.0);
+        [win, 1.0 - win - loss, loss]
+    }
+}
+
+/// win/draw/loss in permille, as output by uci
+pub fn wdl(score: i16, phase: i16) -> [u16; 3] {
+    if is_mate_score(score) {
+        return if score > 0 {
+            [1000, 0, 0]
+        } else {
+            [0, 0, 1000]
+        };
+    }
+    let [win, _, loss] = WDL_PARAMS.probabilities(score as f64, phase);
+    let win = (win * 1000.0).round() as u16;
+    // rounding can take the total over 1000
+    let loss = ((loss * 1000.0).round() as u16).min(1000 - win);
+    [win, 1000 - win - loss, loss]
+}
@@ -0,0 +1,62 @@
+// Fixed depth search of a built-in suite
+// the total node count acts as a signature, so a patch
+// that changes it is not a non-functional change
+
+use crate::position::Position;
+use crate::search::builder::SearchLimits;
+use crate::search::Engine;
+use std::time::Instant;
+
+pub const BENCH_DEPTH: i8 = 9;
+const BENCH_HASH_SIZE: usize = 16;
+
+const BENCH_POSITIONS: [&str; 16] = [
+    // Start Position
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    // Kiwipete Position
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    // Middlegames
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+    "r2q1rk1/1b1nbppp/p2ppn2/1p6/3NP3/1BN1BP2/PPPQ2PP/2KR3R w - - 0 12",
+    "2rq1rk1/pb1nbppp/1p2pn2/2pp4/2PP4/1PN1PN2/PB2BPPP/2RQ1RK1 w - - 0 11",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    // Mate puzzles
+    "rn5r/pp3kpp/2p1R3/5p2/3P4/2B2N2/PPP3PP/2K4n w - - 1 17",
+    "4r1rk/pp4pp/2n5/8/6Q1/7R/1qPK1P1P/3R4 w - - 0 28",
+    // Positions that catch pruning methods out
+    "8/2krR3/1pp3bp/6p1/PPNp4/3P1PKP/8/8 w - - 0 1",
+    "3r2k1/pp3ppp/4p3/8/QP6/P1P5/5KPP/7q w - - 0 27",
+    "1q1r3k/3P1pp1/ppBR1n1p/4Q2P/P4P2/8/5PK1/8 w - - 0 1",
+    // Endgames
+    "8/k7/3p4/p2P1p2/P2P1P2/8/8/K7 w - - 0 1",
+    "8/8/8/4k3/8/8/3PK3/8 w - - 0 1",
+    "6k1/5p2/6p1/8/7p/8/6PP/6K1 b - - 0 1",
+    "8/3k4/8/8/8/4B3/4KN2/8 w - - 0 1",
+];
+
+/// searches every bench position to the given depth with fresh tables,
+/// then reports the total node count and speed
+pub fn bench(depth: i8) {
+    let limits = SearchLimits {
+        depth: Some(depth.max(1)),
+        ..Default::default()
+    };
+    let mut total_nodes = 0;
+    let mut total_time = 0;
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let mut engine = Engine::builder().hash_size(BENCH_HASH_SIZE).build();
+        engine.set_position(Position::from_fen(fen, engine.zobrist_vals()).unwrap());
+        let now = Instant::now();
+        let result = engine.search(&limits);
+        total_time += now.elapsed().as_millis();
+        total_nodes += result.nodes;
+        println!("Position {:>2}: {:>10} nodes  {}", i + 1, result.nodes, fen);
+    }
+    println!("Total time: {}ms", total_time);
+    println!(
+        "{} nodes {} nps",
+        total_nodes,
+        total_nodes as u128 * 1000 / total_time.max(1)
+    );
+}
@@ -0,0 +1,343 @@
+// Command line subcommands, so the engine can be scripted without piping into stdin
+// a fen may be given as one quoted argument, as separate words, or as `startpos`
+
+use super::bench::{bench, BENCH_DEPTH};
+use super::epdsuite::{load_epd_suite, run_epd_suite};
+use super::outputs::{display_eval, display_perft_stats, UciObserver};
+use super::perftsuite::{load_perft_suite, run_perft_suite};
+use super::selfplay::{
+    load_openings, run_match, Adjudication, EngineConfig, MatchConfig, TimeControl,
+};
+use super::sprt::Sprt;
+use super::uci::uci_run;
+use super::xboard::xboard_run;
+use super::{description, run_tuner};
+use crate::position::perft::parallel_perft;
+use crate::position::zobrist::ZobristVals;
+use crate::position::Position;
+use crate::search::builder::{valid_hash_size, SearchLimits};
+use crate::search::Engine;
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub const EXIT_SUCCESS: i32 = 0;
+/// the command was understood, but failed, e.g. an invalid fen or missing file
+pub const EXIT_FAILURE: i32 = 1;
+/// unknown subcommand or malformed arguments
+pub const EXIT_USAGE: i32 = 2;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const USAGE: &str = "usage: kimbo [subcommand]
+  (none)                          interactive console
+  uci                             uci protocol
+  xboard                          xboard protocol
+  perft <fen> <depth> [--threads N] [--hash MB] [--stats]
+                                  leaf count, divided by root move, or
+                                  broken down by move type at every depth
+  perftsuite <file> [max nodes]   check every leaf count in a perft suite
+  bench [depth]                   fixed depth search of the bench suite
+  eval <fen>                      static evaluation, term by term
+  tune <file> [wdl]               texel tuning, or fit the wdl model
+  search <fen> [--depth N] [--nodes N] [--movetime MS]
+                                  search with at least one limit
+  epd <file> [--depth N] [--nodes N] [--movetime MS] [--hash MB] [--threads N]
+                                  run a bm/am/dm test suite, 1s per position
+                                  unless limited
+  match [--engine1 OPTIONS] [--engine2 OPTIONS] [--tc BASE+INC] [--openings FILE]
+        [--pairs N] [--sprt ELO0 ELO1] [--alpha A] [--beta B] [--pgn FILE]
+                                  self-play match, engine options are comma
+                                  separated name=,hash=,threads=,depth=,nodes=
+  help                            this message";
+
+enum CliError {
+    Usage(String),
+    Failure(String),
+}
+
+/// runs a subcommand given the arguments after the program name, returning the exit code
+pub fn run_cli(args: &[String]) -> i32 {
+    let rest = &args[1..];
+    let result = match args[0].as_str() {
+        "uci" => no_args(rest).map(|_| uci_run()),
+        "xboard" => no_args(rest).map(|_| xboard_run()),
+        "perft" => run_perft(rest),
+        "perftsuite" => perft_suite(rest),
+        "bench" => run_bench(rest),
+        "eval" => parse_position(rest).map(|pos| display_eval(&pos)),
+        "tune" => tune(rest),
+        "search" => search(rest),
+        "epd" => epd_suite(rest),
+        "match" => self_play(rest),
+        "help" | "-h" | "--help" => {
+            description();
+            println!("{USAGE}");
+            Ok(())
+        }
+        cmd => Err(CliError::Usage(format!("unknown subcommand '{cmd}'"))),
+    };
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(CliError::Usage(err)) => {
+            eprintln!("error: {err}");
+            eprintln!("{USAGE}");
+            EXIT_USAGE
+        }
+        Err(CliError::Failure(err)) => {
+            eprintln!("error: {err}");
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn no_args(args: &[String]) -> Result<(), CliError> {
+    match args.first() {
+        None => Ok(()),
+        Some(arg) => Err(CliError::Usage(format!("unexpected argument '{arg}'"))),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: Option<&String>) -> Result<T, CliError> {
+    let value = value.ok_or(CliError::Usage(format!("missing {name}")))?;
+    value
+        .parse::<T>()
+        .map_err(|_| CliError::Usage(format!("invalid {name} '{value}'")))
+}
+
+/// hash size in MB, within the range of the UCI Hash option
+fn parse_hash_size(value: Option<&String>) -> Result<usize, CliError> {
+    let value = value.ok_or(CliError::Usage(String::from("missing hash")))?;
+    valid_hash_size(value).map_err(CliError::Usage)
+}
+
+/// index of the first `--option`
+fn options_start(args: &[String]) -> usize {
+    args.iter()
+        .position(|arg| arg.starts_with("--"))
+        .unwrap_or(args.len())
+}
+
+fn fen_string(words: &[String]) -> Result<String, CliError> {
+    match words {
+        [] => Err(CliError::Usage(String::from("missing fen"))),
+        [word] if word == "startpos" => Ok(String::from(STARTPOS)),
+        _ => Ok(words.join(" ")),
+    }
+}
+
+fn parse_position(words: &[String]) -> Result<Position, CliError> {
+    parse_position_with(words, Arc::new(ZobristVals::default()))
+}
+
+fn parse_position_with(words: &[String], zvals: Arc<ZobristVals>) -> Result<Position, CliError> {
+    let fen = fen_string(words)?;
+    Position::from_fen(&fen, zvals).map_err(|_| CliError::Failure(format!("invalid fen '{fen}'")))
+}
+
+/// `perft <fen> <depth> [--threads N] [--hash MB] [--stats]`, the depth follows the fen
+fn run_perft(args: &[String]) -> Result<(), CliError> {
+    let (depth, fen) = args[..options_start(args)]
+        .split_last()
+        .ok_or(CliError::Usage(String::from("missing fen and depth")))?;
+    let depth = parse_number::<u8>("depth", Some(depth))?;
+    let (mut threads, mut hash_size, mut stats) = (1, 0, false);
+    let mut options = args[options_start(args)..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--threads" => threads = parse_number("threads", options.next())?,
+            "--hash" => hash_size = parse_hash_size(options.next())?,
+            "--stats" => stats = true,
+            _ => return Err(CliError::Usage(format!("unknown option '{option}'"))),
+        }
+    }
+    let pos = parse_position(fen)?;
+    if stats {
+        display_perft_stats(&pos, depth);
+        return Ok(());
+    }
+    let now = Instant::now();
+    let count = parallel_perft(&pos, depth, threads, hash_size, false);
+    let elapsed = now.elapsed().as_micros();
+    println!(
+        "Leaf count: {count} ({:.2} ML/sec)",
+        count as f64 / elapsed.max(1) as f64
+    );
+    Ok(())
+}
+
+/// `perftsuite <file> [max nodes]`, fails if any leaf count is wrong
+fn perft_suite(args: &[String]) -> Result<(), CliError> {
+    let (file, max_nodes) = match args {
+        [file] => (file, u64::MAX),
+        [file, max_nodes] => (file, parse_number("max nodes", Some(max_nodes))?),
+        [] => return Err(CliError::Usage(String::from("missing file"))),
+        _ => return no_args(&args[2..]),
+    };
+    let entries = load_perft_suite(file).map_err(|err| CliError::Failure(err.to_string()))?;
+    let report = run_perft_suite(&entries, max_nodes);
+    if !report.passed() {
+        return Err(CliError::Failure(format!(
+            "{} leaf counts were wrong",
+            report.mismatches.len()
+        )));
+    }
+    Ok(())
+}
+
+fn run_bench(args: &[String]) -> Result<(), CliError> {
+    match args {
+        [] => bench(BENCH_DEPTH),
+        [depth] => bench(parse_number("depth", Some(depth))?),
+        _ => return no_args(&args[1..]),
+    }
+    Ok(())
+}
+
+fn tune(args: &[String]) -> Result<(), CliError> {
+    let (file, wdl) = match args {
+        [file] => (file, false),
+        [file, mode] if mode == "wdl" => (file, true),
+        [_, mode] => return Err(CliError::Usage(format!("unknown tuning mode '{mode}'"))),
+        [] => return Err(CliError::Usage(String::from("missing file"))),
+        _ => return no_args(&args[2..]),
+    };
+    // the tuner only reports a missing file, so check first
+    File::open(file).map_err(|err| CliError::Failure(format!("couldn't open '{file}': {err}")))?;
+    let mut commands = vec!["tune", file.as_str()];
+    if wdl {
+        commands.push("wdl");
+    }
+    run_tuner(commands);
+    Ok(())
+}
+
+/// `search <fen> [--depth N] [--nodes N] [--movetime MS]`, reported as uci info lines
+fn search(args: &[String]) -> Result<(), CliError> {
+    let fen_len = options_start(args);
+    let mut limits = SearchLimits::default();
+    let mut options = args[fen_len..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--depth" => limits.depth = Some(parse_number::<i8>("depth", options.next())?.max(1)),
+            "--nodes" => limits.nodes = Some(parse_number("nodes", options.next())?),
+            "--movetime" => limits.move_time = Some(parse_number("movetime", options.next())?),
+            _ => return Err(CliError::Usage(format!("unknown option '{option}'"))),
+        }
+    }
+    if limits.depth.is_none() && limits.nodes.is_none() && limits.move_time.is_none() {
+        return Err(CliError::Usage(String::from(
+            "search needs --depth, --nodes or --movetime",
+        )));
+    }
+
+    let mut engine = Engine::builder()
+        .move_overhead(0)
+        .observer(Arc::new(UciObserver::default()))
+        .build();
+    engine.set_position(parse_position_with(
+        &args[..fen_len],
+        engine.zobrist_vals(),
+    )?);
+    engine.search(&limits);
+    Ok(())
+}
+
+/// `epd <file> [options]`, fails if any position isn't solved
+fn epd_suite(args: &[String]) -> Result<(), CliError> {
+    let file = args
+        .first()
+        .ok_or(CliError::Usage(String::from("missing file")))?;
+    let mut limits = SearchLimits::default();
+    let (mut hash_size, mut threads) = (16, 1);
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--depth" => limits.depth = Some(parse_number::<i8>("depth", options.next())?.max(1)),
+            "--nodes" => limits.nodes = Some(parse_number("nodes", options.next())?),
+            "--movetime" => limits.move_time = Some(parse_number("movetime", options.next())?),
+            "--hash" => hash_size = parse_hash_size(options.next())?,
+            "--threads" => threads = parse_number::<usize>("threads", options.next())?.max(1),
+            _ => return Err(CliError::Usage(format!("unknown option '{option}'"))),
+        }
+    }
+    if limits.depth.is_none() && limits.nodes.is_none() && limits.move_time.is_none() {
+        limits.move_time = Some(1000);
+    }
+
+    let entries = load_epd_suite(file).map_err(|err| CliError::Failure(err.to_string()))?;
+    let report = run_epd_suite(&entries, &limits, hash_size, threads);
+    let failed = report.results.len() - report.solved();
+    if failed > 0 {
+        return Err(CliError::Failure(format!(
+            "{failed} positions weren't solved"
+        )));
+    }
+    Ok(())
+}
+
+/// `match [options]`, plays until the pair limit or an SPRT bound is reached
+fn self_play(args: &[String]) -> Result<(), CliError> {
+    let mut engines = [EngineConfig::new("kimbo-1"), EngineConfig::new("kimbo-2")];
+    let mut time_control = TimeControl {
+        base: 10000,
+        increment: 100,
+    };
+    let mut openings = vec![String::from(STARTPOS)];
+    let mut max_pairs = None;
+    let mut sprt_elo = None;
+    let (mut alpha, mut beta) = (0.05, 0.05);
+    let mut pgn_file = None;
+
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || {
+            options
+                .next()
+                .ok_or_else(|| CliError::Usage(format!("missing value for '{option}'")))
+        };
+        match option.as_str() {
+            "--engine1" | "--engine2" => {
+                let idx = (option == "--engine2") as usize;
+                engines[idx] = engines[idx]
+                    .clone()
+                    .parse_options(value()?)
+                    .map_err(CliError::Usage)?;
+            }
+            "--tc" => {
+                let tc = value()?;
+                time_control = TimeControl::parse(tc)
+                    .ok_or_else(|| CliError::Usage(format!("invalid time control '{tc}'")))?;
+            }
+            "--openings" => openings = load_openings(value()?).map_err(CliError::Failure)?,
+            "--pairs" => max_pairs = Some(parse_number("pairs", Some(value()?))?),
+            "--sprt" => {
+                let elo0 = parse_number::<f64>("elo0", Some(value()?))?;
+                sprt_elo = Some((elo0, parse_number::<f64>("elo1", Some(value()?))?));
+            }
+            "--alpha" => alpha = parse_number("alpha", Some(value()?))?,
+            "--beta" => beta = parse_number("beta", Some(value()?))?,
+            "--pgn" => pgn_file = Some(value()?.clone()),
+            _ => return Err(CliError::Usage(format!("unknown option '{option}'"))),
+        }
+    }
+
+    let sprt = sprt_elo.map(|(elo0, elo1)| Sprt {
+        elo0,
+        elo1,
+        alpha,
+        beta,
+    });
+    let config = MatchConfig {
+        engines,
+        time_control,
+        openings,
+        // an SPRT runs until it finishes, unless limited
+        max_pairs: max_pairs.unwrap_or(if sprt.is_some() { usize::MAX } else { 50 }),
+        sprt,
+        adjudication: Adjudication::default(),
+        pgn_file,
+    };
+    run_match(&config).map_err(CliError::Failure)?;
+    Ok(())
+}
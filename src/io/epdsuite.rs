@@ -0,0 +1,294 @@
+// Tactical test suites in EPD, e.g. WAC or ECM
+// <board> <side> <castling> <en passant> <opcode> <operands>; ...
+// bm (best moves) and am (moves to avoid) take SAN moves, dm is the
+// length of a forced mate and id names the position
+
+use super::errors::EpdSuiteError;
+use crate::position::zobrist::ZobristVals;
+use crate::position::Position;
+use crate::search::builder::SearchLimits;
+use crate::search::observer::{IterationInfo, SearchObserver};
+use crate::search::result::Score;
+use crate::search::Engine;
+use crate::tables::search::Bound;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+pub struct EpdEntry {
+    pub id: String,
+    pub fen: String,
+    /// any of these solves the position
+    pub best_moves: Vec<u16>,
+    /// none of these may be played
+    pub avoid_moves: Vec<u16>,
+    /// mate must be found in at most this many moves
+    pub mate: Option<i16>,
+}
+
+impl EpdEntry {
+    /// whether a move and its score satisfy every operation
+    pub fn is_solution(&self, m: u16, score: Score) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&m))
+            && !self.avoid_moves.contains(&m)
+            && self.mate.is_none_or(
+                |moves| matches!(score, Score::Mate(found) if found > 0 && found <= moves),
+            )
+    }
+}
+
+pub struct EpdResult {
+    pub id: String,
+    pub solved: bool,
+    /// move played, in SAN
+    pub found: String,
+    pub score: Score,
+    /// first depth from which every iteration found a solution
+    pub depth: i8,
+    /// milliseconds until that depth was completed
+    pub time: u128,
+    pub nodes: u64,
+}
+
+#[derive(Default)]
+pub struct EpdSuiteReport {
+    pub results: Vec<EpdResult>,
+    pub nodes: u64,
+    /// milliseconds
+    pub time: u128,
+}
+
+impl EpdSuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &EpdResult> {
+        self.results.iter().filter(|result| !result.solved)
+    }
+}
+
+/// splits on a separator, ignoring any inside quotes
+fn split_unquoted(text: &str, is_separator: impl Fn(char) -> bool) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quoted = false;
+    for ch in text.chars() {
+        if ch == '"' {
+            quoted = !quoted;
+        } else if !quoted && is_separator(ch) {
+            parts.push(std::mem::take(&mut part));
+        } else {
+            part.push(ch);
+        }
+    }
+    parts.push(part);
+    parts.retain(|part| !part.trim().is_empty());
+    parts
+}
+
+fn parse_entry(line: &str, zvals: &Arc<ZobristVals>) -> Result<EpdEntry, String> {
+    let fields: Vec<&str> = line.splitn(5, char::is_whitespace).collect();
+    if fields.len() < 4 {
+        return Err(String::from(
+            "expected board, side, castling and en passant fields",
+        ));
+    }
+    let mut operations = split_unquoted(fields.get(4).unwrap_or(&""), |ch| ch == ';');
+
+    // full fens have their move counters before the operations
+    let mut counters = String::from("0 1");
+    if let Some(first) = operations.first() {
+        let words: Vec<&str> = first.split_whitespace().collect();
+        if words.len() > 2 && words[..2].iter().all(|word| word.parse::<u16>().is_ok()) {
+            counters = words[..2].join(" ");
+            operations[0] = words[2..].join(" ");
+        }
+    }
+
+    let mut entry = EpdEntry {
+        id: String::new(),
+        fen: String::new(),
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+        mate: None,
+    };
+    let mut parsed = Vec::new();
+    for operation in &operations {
+        let operands = split_unquoted(operation, char::is_whitespace);
+        let opcode = operands[0].clone();
+        match opcode.as_str() {
+            "hmvc" | "fmvn" => {
+                let value = operands.get(1).filter(|value| value.parse::<u16>().is_ok());
+                let value = value.ok_or_else(|| format!("invalid {opcode}"))?;
+                let mut words: Vec<String> = counters.split(' ').map(String::from).collect();
+                words[(opcode == "fmvn") as usize] = value.clone();
+                counters = words.join(" ");
+            }
+            _ => parsed.push(operands),
+        }
+    }
+    entry.fen = format!("{} {}", fields[..4].join(" "), counters);
+    let pos = Position::from_fen(&entry.fen, zvals.clone())
+        .map_err(|_| format!("invalid position '{}'", entry.fen))?;
+
+    for operands in parsed {
+        let (opcode, operands) = (operands[0].as_str(), &operands[1..]);
+        let moves = || {
+            operands
+                .iter()
+                .map(|san| pos.parse_san(san).map_err(|err| err.to_string()))
+                .collect::<Result<Vec<u16>, String>>()
+        };
+        match opcode {
+            "id" => entry.id = operands.join(" "),
+            "bm" => entry.best_moves = moves()?,
+            "am" => entry.avoid_moves = moves()?,
+            "dm" => {
+                let mate = operands.first().and_then(|value| value.parse().ok());
+                entry.mate = Some(mate.filter(|&moves| moves > 0).ok_or("invalid dm")?);
+            }
+            // comments, engine names and the like
+            _ => (),
+        }
+    }
+    if entry.best_moves.is_empty() && entry.avoid_moves.is_empty() && entry.mate.is_none() {
+        return Err(String::from("no bm, am or dm operation"));
+    }
+    Ok(entry)
+}
+
+/// parses a whole suite, checking every position and move,
+/// positions without an id are named by their line number
+pub fn parse_epd_suite(text: &str) -> Result<Vec<EpdEntry>, EpdSuiteError> {
+    let zvals = Arc::new(ZobristVals::default());
+    let mut entries = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut entry =
+            parse_entry(line, &zvals).map_err(|reason| EpdSuiteError::Line(idx + 1, reason))?;
+        if entry.id.is_empty() {
+            entry.id = format!("line {}", idx + 1);
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+pub fn load_epd_suite(path: &str) -> Result<Vec<EpdEntry>, EpdSuiteError> {
+    parse_epd_suite(&fs::read_to_string(path)?)
+}
+
+/// Keeps the best move and score of every completed iteration
+#[derive(Default)]
+struct IterationLog(Mutex<Vec<(i8, u128, u16, Score)>>);
+impl SearchObserver for IterationLog {
+    fn on_iteration(&self, info: &IterationInfo) {
+        if info.multipv == 1 && info.bound == Bound::EXACT && !info.pv.is_empty() {
+            let iteration = (info.depth, info.time, info.pv[0], Score::from(info.score));
+            self.0.lock().unwrap().push(iteration);
+        }
+    }
+}
+
+fn describe(entry: &EpdEntry, pos: &Position) -> String {
+    let sans = |moves: &[u16]| {
+        let sans: Vec<String> = moves.iter().map(|&m| pos.move_to_san(m)).collect();
+        sans.join(" ")
+    };
+    let mut expected = Vec::new();
+    if !entry.best_moves.is_empty() {
+        expected.push(format!("bm {}", sans(&entry.best_moves)));
+    }
+    if !entry.avoid_moves.is_empty() {
+        expected.push(format!("am {}", sans(&entry.avoid_moves)));
+    }
+    if let Some(moves) = entry.mate {
+        expected.push(format!("dm {}", moves));
+    }
+    expected.join(", ")
+}
+
+/// searches every position from a new game, printing each result and a summary
+pub fn run_epd_suite(
+    entries: &[EpdEntry],
+    limits: &SearchLimits,
+    hash_size: usize,
+    threads: usize,
+) -> EpdSuiteReport {
+    let log = Arc::new(IterationLog::default());
+    let mut engine = Engine::builder()
+        .hash_size(hash_size)
+        .threads(threads)
+        .move_overhead(0)
+        .observer(log.clone())
+        .build();
+    let zvals = engine.zobrist_vals();
+    let mut report = EpdSuiteReport::default();
+    for entry in entries {
+        let pos = Position::from_fen(&entry.fen, zvals.clone()).unwrap();
+        engine.new_game();
+        engine.set_position(pos.clone());
+        log.0.lock().unwrap().clear();
+        let result = engine.search(limits);
+        report.nodes += result.nodes;
+        report.time += result.time;
+
+        let solved = entry.is_solution(result.best_move, result.score);
+        let (mut depth, mut time) = (result.depth, result.time);
+        if solved {
+            for &(iter_depth, iter_time, m, score) in log.0.lock().unwrap().iter().rev() {
+                if !entry.is_solution(m, score) {
+                    break;
+                }
+                (depth, time) = (iter_depth, iter_time);
+            }
+        }
+        let found = if result.best_move == 0 {
+            String::from("(none)")
+        } else {
+            pos.move_to_san(result.best_move)
+        };
+        if solved {
+            println!(
+                "solved {}: {} at depth {}, {}ms",
+                entry.id, found, depth, time
+            );
+        } else {
+            println!(
+                "failed {}: {}, expected {}",
+                entry.id,
+                found,
+                describe(entry, &pos)
+            );
+        }
+        report.results.push(EpdResult {
+            id: entry.id.clone(),
+            solved,
+            found,
+            score: result.score,
+            depth,
+            time,
+            nodes: result.nodes,
+        });
+    }
+
+    let solved = report.solved();
+    let solve_time: u128 = report
+        .results
+        .iter()
+        .filter(|result| result.solved)
+        .map(|result| result.time)
+        .sum();
+    println!(
+        "{}/{} solved, {} nodes in {}ms, average solve time {}ms",
+        solved,
+        report.results.len(),
+        report.nodes,
+        report.time,
+        solve_time / solved.max(1) as u128
+    );
+    report
+}
@@ -1,5 +1,4 @@
-use super::fen::FenError;
-use std::{fmt, num::ParseIntError};
+use std::{fmt, io, num::ParseIntError};
 
 #[derive(Debug)]
 pub enum UciError {
@@ -7,20 +6,27 @@ pub enum UciError {
     Display,
     SetOption,
     Go,
-    Position,
-    Move,
-    Fen,
+    Position(String),
+    Move(String),
+    Fen(String),
+    Unknown(String),
+    /// a value outside the accepted range, with the reason
+    Range(String),
 }
 impl fmt::Display for UciError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             Self::Value => write!(f, "error parsing value as integer"),
             Self::Display => write!(f, "error parsing 'display' command"),
             Self::SetOption => write!(f, "error parsing 'setoption' command"),
             Self::Go => write!(f, "error parsing 'go' command"),
-            Self::Position => write!(f, "error parsing 'position' command"),
-            Self::Move => write!(f, "error parsing 'moves' list"),
-            Self::Fen => write!(f, "error parsing 'fen' string"),
+            Self::Position(token) => {
+                write!(f, "error parsing 'position' command at '{}'", token)
+            }
+            Self::Move(m) => write!(f, "error parsing 'moves' list at '{}'", m),
+            Self::Fen(fen) => write!(f, "error parsing 'fen' string '{}'", fen),
+            Self::Unknown(command) => write!(f, "unknown command '{}'", command),
+            Self::Range(reason) => write!(f, "{}", reason),
         }
     }
 }
@@ -30,8 +36,106 @@ impl From<ParseIntError> for UciError {
         Self::Value
     }
 }
-impl From<FenError> for UciError {
-    fn from(_: FenError) -> Self {
-        Self::Fen
+
+#[derive(Debug)]
+pub enum PerftSuiteError {
+    Io(io::Error),
+    /// line number, from 1, and the reason it couldn't be parsed
+    Line(usize, String),
+}
+impl fmt::Display for PerftSuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "error reading perft suite: {}", err),
+            Self::Line(line, reason) => {
+                write!(f, "error on line {} of perft suite: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for PerftSuiteError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum EpdSuiteError {
+    Io(io::Error),
+    /// line number, from 1, and the reason it couldn't be parsed
+    Line(usize, String),
+}
+impl fmt::Display for EpdSuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "error reading epd suite: {}", err),
+            Self::Line(line, reason) => {
+                write!(f, "error on line {} of epd suite: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for EpdSuiteError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// not a move in any notation
+    Invalid(String),
+    /// no legal move matches
+    Illegal(String),
+    /// more than one legal move matches
+    Ambiguous(String),
+}
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(san) => write!(f, "invalid move '{}'", san),
+            Self::Illegal(san) => write!(f, "illegal move '{}'", san),
+            Self::Ambiguous(san) => write!(f, "ambiguous move '{}'", san),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    /// line number, and the malformed tag pair
+    Tag(usize, String),
+    /// line number, and the setup position
+    Fen(usize, String),
+    /// a move that couldn't be played, with its move number as written, e.g. 12...
+    Move {
+        line: usize,
+        move_number: String,
+        error: SanError,
+    },
+    /// line number, and what is wrong with the nesting of (variations)
+    Variation(usize, &'static str),
+}
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "error reading pgn: {}", err),
+            Self::Tag(line, tag) => write!(f, "line {}: malformed tag '{}'", line, tag),
+            Self::Fen(line, fen) => write!(f, "line {}: invalid FEN tag '{}'", line, fen),
+            Self::Move {
+                line,
+                move_number,
+                error,
+            } => write!(f, "line {}: {} at {}", line, error, move_number),
+            Self::Variation(line, reason) => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
     }
 }
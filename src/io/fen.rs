@@ -2,6 +2,7 @@
 // other way is handled in kimbo_state
 
 use super::outputs::idx_to_sq;
+use super::FILES;
 use crate::eval::{calc_material, calc_pst, calculate_phase};
 use crate::position::{
     zobrist::{initialise_pawnhash, initialise_zobrist, ZobristVals},
@@ -50,14 +51,25 @@ fn board(sqs: [u8; 64], sides: [u64; 2]) -> String {
     fen
 }
 
-fn castle_rights(rights: u8) -> String {
+/// X-FEN style, rooks off their standard squares (Chess960) are given by file
+fn castle_rights(rights: u8, rooks: [u16; 4]) -> String {
     if rights == 0 {
         return String::from("-");
     }
     let mut s = String::from("");
     for i in 0..4 {
         if rights & RIGHTS[i] > 0 {
-            s.push(RIGHTS_CHAR[i]);
+            let idx = ls1b_scan(RIGHTS[i] as u64) as usize;
+            if rooks[idx] == STANDARD_CASTLE_ROOKS[idx] {
+                s.push(RIGHTS_CHAR[i]);
+            } else {
+                let file = FILES[(rooks[idx] & 7) as usize];
+                s.push(if i < 2 {
+                    file.to_ascii_uppercase()
+                } else {
+                    file
+                });
+            }
         }
     }
     s
@@ -69,7 +81,7 @@ impl Position {
             "{} {} {} {} {} {}",
             board(self.squares, self.sides),
             SIDES[self.side_to_move],
-            castle_rights(self.castle_rights),
+            castle_rights(self.castle_rights, self.castle_rooks),
             match self.en_passant_sq {
                 0 => String::from("-"),
                 _ => idx_to_sq(self.en_passant_sq),
@@ -148,9 +160,7 @@ fn piece_idxs(s: &str) -> Result<[[Vec<usize>; 6]; 2], FenError> {
                 count += 1;
                 let (col, pc) = piece_in(ch)?;
                 piece_idxs[col][pc].push(idx);
-                if idx > 0 {
-                    idx -= 1;
-                }
+                idx = idx.saturating_sub(1);
             }
             // skip empty squares
             else {
@@ -184,29 +194,54 @@ fn get_pieces(s: &str) -> Result<[[u64; 6]; 2], FenError> {
 fn get_sides(pieces: [[u64; 6]; 2]) -> [u64; 2] {
     let mut sides = [0; 2];
     for side in 0..2 {
-        for pc in 0..6 {
-            sides[side] |= pieces[side][pc];
+        for bb in pieces[side] {
+            sides[side] |= bb;
         }
     }
     sides
 }
 
-fn get_castling_rights(s: &str) -> Result<u8, FenError> {
+/// accepts standard, X-FEN and Shredder-FEN castling fields
+fn get_castling_rights(s: &str, pieces: &[[u64; 6]; 2]) -> Result<(u8, [u16; 4]), FenError> {
+    let mut rooks = STANDARD_CASTLE_ROOKS;
     if s == "-" {
-        return Ok(CastleRights::NONE);
+        return Ok((CastleRights::NONE, rooks));
     }
     let mut castle = CastleRights::NONE;
     for ch in s.chars() {
-        let right = match ch {
-            'Q' => CastleRights::WHITE_QS,
-            'K' => CastleRights::WHITE_KS,
-            'q' => CastleRights::BLACK_QS,
-            'k' => CastleRights::BLACK_KS,
+        let side = ch.is_ascii_lowercase() as usize;
+        let rank = 56 * side;
+        let king = pieces[side][Piece::KING] & (0xFF << rank);
+        let king_file = if king > 0 {
+            (ls1b_scan(king) & 7) as usize
+        } else {
+            4
+        };
+        let rook_on = |file: &usize| pieces[side][Piece::ROOK] & (1 << (rank + file)) > 0;
+        // K and Q refer to the outermost rook on that side of the king
+        let (flag, file) = match ch.to_ascii_lowercase() {
+            'k' => (MoveFlags::KS_CASTLE, (king_file + 1..8).rev().find(rook_on)),
+            'q' => (MoveFlags::QS_CASTLE, (0..king_file).find(rook_on)),
+            file @ 'a'..='h' => {
+                let file = file as usize - 'a' as usize;
+                let flag = if file > king_file {
+                    MoveFlags::KS_CASTLE
+                } else {
+                    MoveFlags::QS_CASTLE
+                };
+                (flag, Some(file).filter(rook_on))
+            }
             _ => return Err(FenError),
         };
+        // rights without a king on the back rank and a rook to castle with are dropped
+        let Some(file) = file.filter(|_| king > 0) else {
+            continue;
+        };
+        let right = CastleRights::right(side, flag);
         castle |= right;
+        rooks[ls1b_scan(right as u64) as usize] = (rank + file) as u16;
     }
-    Ok(castle)
+    Ok((castle, rooks))
 }
 
 fn get_square(s: &str) -> Result<u16, FenError> {
@@ -219,6 +254,9 @@ fn get_square(s: &str) -> Result<u16, FenError> {
     }
     let x = arr[0] as char;
     let y = arr[1] as char;
+    if !('1'..='8').contains(&y) {
+        return Err(FenError);
+    }
     let file = match x {
         'A' | 'a' => 0,
         'B' | 'b' => 1,
@@ -231,7 +269,7 @@ fn get_square(s: &str) -> Result<u16, FenError> {
         _ => return Err(FenError),
     };
     let rank: u16 = y.to_string().parse::<u16>()? - 1;
-    Ok((8 * rank + file) as u16)
+    Ok(8 * rank + file)
 }
 
 impl Position {
@@ -243,6 +281,13 @@ impl Position {
             return Err(FenError);
         }
         let pieces = get_pieces(vec[0])?;
+        // exactly one king each, or move generation has nothing to anchor to
+        if pieces
+            .iter()
+            .any(|side| side[Piece::KING].count_ones() != 1)
+        {
+            return Err(FenError);
+        }
         let sides = get_sides(pieces);
         let occupied = sides[0] | sides[1];
         let squares = pieces_to_squares(pieces);
@@ -251,7 +296,7 @@ impl Position {
             "b" => Side::BLACK,
             _ => return Err(FenError),
         };
-        let castle_rights = get_castling_rights(vec[2])?;
+        let (castle_rights, castle_rooks) = get_castling_rights(vec[2], &pieces)?;
         let en_passant_sq = get_square(vec[3])?;
         let mut halfmove_clock = 0;
         let mut fullmove_counter = 1;
@@ -273,6 +318,7 @@ impl Position {
             halfmove_clock,
             fullmove_counter,
             zobrist_vals,
+            castle_rooks,
             state_stack: Vec::new(),
             null_counter: 0,
             pawnhash: 0,
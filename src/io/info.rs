@@ -6,13 +6,16 @@ Move Generation:
 - Bitboards
 - Fully-legal
 - Hyperbola quintessence sliding attacks
+- Chess960 (X-FEN and Shredder-FEN)
 
 Search:
 - Fail-soft
 - Principle variation search
 - Quiescence search
 - Iterative deepening
+- Aspiration windows
 - Check extensions
+- Lazy SMP
 
 Move Ordering:
 1. Hash move
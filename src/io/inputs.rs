@@ -1,26 +1,36 @@
 use super::errors::UciError;
+use super::outputs::castle_as_king_move;
 use super::FILES;
 use crate::position::{MoveList, MoveType, Position};
+use std::io;
 
-fn sq_to_idx(sq: &str) -> Result<u16, UciError> {
+/// reads a line from stdin, returning None once input is closed or unreadable
+pub fn read_input() -> Option<String> {
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+pub fn sq_to_idx(sq: &str) -> Option<u16> {
     let chs: Vec<char> = sq.chars().collect();
-    let file: u16 = match FILES.iter().position(|&ch| ch == chs[0]) {
-        Some(res) => res as u16,
-        None => return Err(UciError::Move),
-    };
-    let rank = chs[1].to_string().parse::<u16>()? - 1;
-    Ok(8 * rank + file)
+    let file = FILES.iter().position(|&ch| ch == chs[0])? as u16;
+    let rank = chs[1].to_digit(10).filter(|rank| (1..=8).contains(rank))? as u16 - 1;
+    Some(8 * rank + file)
 }
 
 const TWELVE: u16 = 0b0000_1111_1111_1111;
 
-pub fn uci_to_u16(pos: &Position, m: &str) -> Result<u16, UciError> {
+/// castles are accepted king-takes-rook, and outside of Chess960 mode also as the king's move
+pub fn uci_to_u16(pos: &Position, m: &str, chess960: bool) -> Result<u16, UciError> {
     let l = m.len();
-    if !(l == 4 || l == 5) {
-        return Err(UciError::Move);
+    let invalid = || UciError::Move(m.to_string());
+    if !(l == 4 || l == 5) || !m.is_ascii() {
+        return Err(invalid());
     }
-    let from = sq_to_idx(&m[0..2])?;
-    let to = sq_to_idx(&m[2..4])?;
+    let from = sq_to_idx(&m[0..2]).ok_or_else(invalid)?;
+    let to = sq_to_idx(&m[2..4]).ok_or_else(invalid)?;
     let mut no_flags = from | (to << 6);
     if l == 5 {
         no_flags |= match m.chars().nth(4).unwrap() {
@@ -28,14 +38,16 @@ pub fn uci_to_u16(pos: &Position, m: &str) -> Result<u16, UciError> {
             'b' => 0b1001_0000_0000_0000,
             'r' => 0b1010_0000_0000_0000,
             'q' => 0b1011_0000_0000_0000,
-            _ => return Err(UciError::Move),
+            _ => return Err(invalid()),
         }
     }
     let mut possible_moves = MoveList::default();
     pos.gen_moves::<{ MoveType::ALL }>(&mut possible_moves);
     for m_idx in 0..possible_moves.len() {
         let um = possible_moves[m_idx];
-        if no_flags & TWELVE == um & TWELVE {
+        if no_flags & TWELVE == um & TWELVE
+            || no_flags & TWELVE == castle_as_king_move(um, chess960) & TWELVE
+        {
             if l < 5 {
                 return Ok(um);
             }
@@ -44,5 +56,5 @@ pub fn uci_to_u16(pos: &Position, m: &str) -> Result<u16, UciError> {
             }
         }
     }
-    Err(UciError::Move)
+    Err(invalid())
 }
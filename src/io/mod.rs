@@ -1,3 +1,9 @@
+/// fixed depth benchmark
+pub mod bench;
+/// command line subcommands
+pub mod cli;
+/// tactical test suites
+pub mod epdsuite;
 /// error handling
 pub mod errors;
 pub mod fen;
@@ -5,17 +11,33 @@ mod info;
 pub mod inputs;
 /// internal representations to readable outputs
 pub mod outputs;
+/// perft test suites
+pub mod perftsuite;
+/// reading and writing games
+pub mod pgn;
+/// standard algebraic notation
+pub mod san;
+/// self-play matches
+pub mod selfplay;
+/// sequential probability ratio test
+pub mod sprt;
 /// uci interface
 pub mod uci;
+/// xboard interface
+pub mod xboard;
 
+use bench::{bench, BENCH_DEPTH};
 use info::*;
+use inputs::read_input;
 use outputs::u16_to_uci;
-use std::io;
 use std::process;
 use uci::uci_run;
+use xboard::xboard_run;
 
-use crate::eval::tuner::optimise;
+use crate::eval::tuner::{optimise, optimise_wdl};
 use crate::eval::tuner_eval::ParamContainer;
+use crate::eval::wdl::WDL_PARAMS;
+use crate::search::result::SearchResult;
 
 // used in inputs/outputs
 const FILES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
@@ -29,30 +51,52 @@ fn features() {
     println!("{}", FEATURES);
 }
 
-/// run Texel tuner
+/// run Texel tuner, or fit the win/draw/loss model with `tune <file> wdl`
 fn run_tuner(commands: Vec<&str>) {
     if !(2..=3).contains(&commands.len()) {
         println!("invalid command");
         return;
     }
+    if commands.get(2) == Some(&"wdl") {
+        let best = optimise_wdl(commands[1], WDL_PARAMS);
+        println!("Best WDL parameters:");
+        println!("{:#?}", best);
+        return;
+    }
     let initial_params = ParamContainer::default();
     let best = optimise::<true>(commands[1], initial_params);
     println!("Best parameters:");
     println!("{:#?}", best);
 }
 
+/// run bench, optionally to a given depth
+fn run_bench(commands: Vec<&str>) {
+    match commands.get(1).map(|depth| depth.parse::<i8>()) {
+        None => bench(BENCH_DEPTH),
+        Some(Ok(depth)) => bench(depth),
+        Some(Err(_)) => println!("invalid command"),
+    }
+}
+
 pub fn main_loop() {
     println!("Kimbo, created by Jamie Whiting");
     loop {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let commands: Vec<&str> = input.split(' ').map(|v| v.trim()).collect();
+        let input = match read_input() {
+            Some(input) => input,
+            None => process::exit(0),
+        };
+        let commands: Vec<&str> = input.split_whitespace().collect();
+        if commands.is_empty() {
+            continue;
+        }
         match commands[0] {
             "uci" => uci_run(),
+            "xboard" => xboard_run(),
             "quit" => process::exit(0),
             "description" => description(),
             "features" => features(),
             "tune" => run_tuner(commands),
+            "bench" => run_bench(commands),
             _ => println!("Unknown command!"),
         }
     }
@@ -66,6 +110,17 @@ pub struct SearchStats {
     pv: Vec<u16>,
 }
 
+impl From<&SearchResult> for SearchStats {
+    fn from(result: &SearchResult) -> Self {
+        Self::new(
+            result.depth,
+            result.time as u64,
+            result.nodes,
+            result.pv.clone(),
+        )
+    }
+}
+
 impl SearchStats {
     pub fn new(depth_reached: i8, time_to_depth: u64, nodes_to_depth: u64, pv: Vec<u16>) -> Self {
         Self {
@@ -81,6 +136,12 @@ impl SearchStats {
             "depth reached {} nodes {} time {}",
             self.depth_reached, self.nodes_to_depth, self.time_to_depth
         );
-        println!("pv {}", self.pv.iter().map(u16_to_uci).collect::<String>());
+        println!(
+            "pv {}",
+            self.pv
+                .iter()
+                .map(|m| u16_to_uci(m, false))
+                .collect::<String>()
+        );
     }
 }
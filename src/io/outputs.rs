@@ -1,7 +1,10 @@
 use super::FILES;
-use crate::position::{MoveList, Position};
-use crate::search::{is_mate_score, MAX_SCORE};
-
+use crate::position::consts::TOTALPHASE;
+use crate::position::perft::perft_stats;
+use crate::position::{MoveFlags, MoveList, Position};
+use crate::search::observer::{IterationInfo, SearchObserver};
+use crate::search::result::{Score, SearchResult};
+use crate::tables::search::Bound;
 /// board idx to square
 pub fn idx_to_sq(idx: u16) -> String {
     let rank = idx >> 3;
@@ -11,10 +14,22 @@ pub fn idx_to_sq(idx: u16) -> String {
     format!("{sfile}{srank}")
 }
 
+/// castles are encoded king-takes-rook, outside of Chess960 mode
+/// they are written as the king's move, e.g. e1g1
+pub fn castle_as_king_move(m: u16, chess960: bool) -> u16 {
+    let flag = m & MoveFlags::ALL;
+    if (flag != MoveFlags::KS_CASTLE && flag != MoveFlags::QS_CASTLE) || chess960 {
+        return m;
+    }
+    let file = if flag == MoveFlags::KS_CASTLE { 6 } else { 2 };
+    flag | ((m & 56) | file) << 6 | (m & 63)
+}
+
 /// u16 move format to uci move format
 const PROMOS: [&str; 4] = ["n", "b", "r", "q"];
 const PROMO_BIT: u16 = 0b1000_0000_0000_0000;
-pub fn u16_to_uci(m: &u16) -> String {
+pub fn u16_to_uci(m: &u16, chess960: bool) -> String {
+    let m = castle_as_king_move(*m, chess960);
     let mut promo = "";
     if m & PROMO_BIT > 0 {
         promo = PROMOS[((m >> 12) & 0b11) as usize];
@@ -28,37 +43,128 @@ pub fn u16_to_uci(m: &u16) -> String {
 }
 
 /// returns info on the search
-pub fn uci_info(
-    depth: i8,
-    seldepth: i8,
-    nodes: u64,
-    time: u128,
-    pv: Vec<u16>,
-    eval: i16,
-    hashfull: u64,
-) {
-    let pv_str: String = pv.iter().map(u16_to_uci).collect();
-    let mut score_type = "cp";
-    let mut score = eval;
-    if is_mate_score(eval) {
-        score_type = "mate";
-        score = if eval < 0 {
-            eval.abs() - MAX_SCORE
-        } else {
-            MAX_SCORE - eval + 1
-        } / 2;
-    }
-    let nps = if time != 0 {
+pub fn uci_info(info: &IterationInfo, chess960: bool) {
+    let pv_str: String = info.pv.iter().map(|m| u16_to_uci(m, chess960)).collect();
+    let (score_type, score) = match Score::from(info.score) {
+        Score::Cp(cp) => ("cp", cp),
+        Score::Mate(moves) => ("mate", moves),
+    };
+    let bound_str = match info.bound {
+        Bound::LOWER => " lowerbound",
+        Bound::UPPER => " upperbound",
+        _ => "",
+    };
+    let wdl_str = match info.wdl {
+        Some([w, d, l]) => format!(" wdl {w} {d} {l}"),
+        None => String::new(),
+    };
+    println!(
+        "info depth {} seldepth {} multipv {} score {} {}{}{} time {} nodes {} nps {} hashfull {} pv {}",
+        info.depth,
+        info.seldepth,
+        info.multipv,
+        score_type,
+        score,
+        bound_str,
+        wdl_str,
+        info.time,
+        info.nodes,
+        nps(info.nodes, info.time),
+        info.hashfull,
+        pv_str
+    );
+}
+
+/// xboard thinking output: ply, score, time in centiseconds, nodes and pv,
+/// with mates given as 100000 + moves to mate
+pub fn xboard_thinking(info: &IterationInfo) {
+    let pv_str: String = info.pv.iter().map(|m| u16_to_uci(m, false)).collect();
+    let score = match Score::from(info.score) {
+        Score::Cp(cp) => cp as i32,
+        Score::Mate(moves) => 100000 * moves.signum() as i32 + moves as i32,
+    };
+    println!(
+        "{} {} {} {} {}",
+        info.depth,
+        score,
+        info.time / 10,
+        info.nodes,
+        pv_str.trim_end()
+    );
+}
+
+fn nps(nodes: u64, time: u128) -> u32 {
+    if time != 0 {
         ((nodes as f64) / ((time as f64) / 1000.0)) as u32
     } else {
         nodes as u32 * 1000
-    };
+    }
+}
+
+/// reports the root move currently being searched
+pub fn uci_currmove(m: u16, number: usize, chess960: bool) {
     println!(
-        "info depth {} seldepth {} score {} {} time {} nodes {} nps {} hashfull {} pv {}",
-        depth, seldepth, score_type, score, time, nodes, nps, hashfull, pv_str
+        "info currmove {}currmovenumber {}",
+        u16_to_uci(&m, chess960),
+        number
     );
 }
 
+/// periodic report of search progress
+pub fn uci_heartbeat(nodes: u64, time: u128, hashfull: u64) {
+    println!(
+        "info time {} nodes {} nps {} hashfull {}",
+        time,
+        nodes,
+        nps(nodes, time),
+        hashfull
+    );
+}
+
+/// reports the search result, a null move (no legal moves) is written 0000
+pub fn uci_bestmove(best_move: u16, ponder_move: u16, chess960: bool) {
+    if best_move == 0 {
+        println!("bestmove 0000");
+        return;
+    }
+    let mut output = format!("bestmove {}", u16_to_uci(&best_move, chess960));
+    if ponder_move > 0 {
+        output.push_str(&format!("ponder {}", u16_to_uci(&ponder_move, chess960)));
+    }
+    println!("{}", output.trim_end());
+}
+
+/// UCI text output, with castles written king-takes-rook in UCI_Chess960 mode
+#[derive(Default)]
+pub struct UciObserver {
+    pub chess960: bool,
+}
+impl SearchObserver for UciObserver {
+    fn on_iteration(&self, info: &IterationInfo) {
+        uci_info(info, self.chess960)
+    }
+    fn on_currmove(&self, m: u16, number: usize) {
+        uci_currmove(m, number, self.chess960)
+    }
+    fn on_progress(&self, nodes: u64, time: u128, hashfull: u64) {
+        uci_heartbeat(nodes, time, hashfull)
+    }
+    fn on_bestmove(&self, result: &SearchResult) {
+        uci_bestmove(result.best_move, result.ponder_move, self.chess960)
+    }
+}
+
+/// xboard thinking output, for 'post'
+/// only exact scores on the main line, moves are sent by the xboard loop
+pub struct XboardObserver;
+impl SearchObserver for XboardObserver {
+    fn on_iteration(&self, info: &IterationInfo) {
+        if info.multipv == 1 && info.bound == Bound::EXACT {
+            xboard_thinking(info)
+        }
+    }
+}
+
 // getting symbols for pieces
 const PIECE_SYMBOLS: [&str; 13] = [
     " ", "P", "N", "B", "R", "Q", "K", "p", "n", "b", "r", "q", "k",
@@ -86,9 +192,9 @@ pub fn display_board(pos: &Position) {
     }
 }
 
-pub fn display_movelist(moves: &MoveList) {
+pub fn display_movelist(moves: &MoveList, chess960: bool) {
     for i in 0..moves.len() {
-        println!("{}", u16_to_uci(&moves[i]))
+        println!("{}", u16_to_uci(&moves[i], chess960))
     }
 }
 
@@ -99,6 +205,76 @@ pub fn report_stats(pos: &Position) {
     println!("state stack length: {}", pos.state_stack.len());
 }
 
+/// perft statistics for each depth up to the given one,
+/// in the column order of the chessprogramming wiki perft tables
+pub fn display_perft_stats(pos: &Position, depth: u8) {
+    println!(
+        "{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>9} {:>10} {:>8} {:>10}",
+        "Depth",
+        "Nodes",
+        "Captures",
+        "E.p.",
+        "Castles",
+        "Promotions",
+        "Checks",
+        "Discovery",
+        "Double",
+        "Checkmates"
+    );
+    let mut pos = pos.clone();
+    for d in 1..=depth {
+        let stats = perft_stats(&mut pos, d);
+        println!(
+            "{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>9} {:>10} {:>8} {:>10}",
+            d,
+            stats.nodes,
+            stats.captures,
+            stats.en_passant,
+            stats.castles,
+            stats.promotions,
+            stats.checks,
+            stats.discovered_checks,
+            stats.double_checks,
+            stats.checkmates
+        );
+    }
+}
+
+/// table of every evaluation term, by side and by midgame/endgame
+pub fn display_eval(pos: &Position) {
+    let trace = pos.eval_trace();
+    let line = "-------------+-------------------+-------------------+-------------------+--------";
+    println!(
+        "{:<12} | {:>8} {:>8} | {:>8} {:>8} | {:>8} {:>8} | {:>7}",
+        "Term", "White MG", "White EG", "Black MG", "Black EG", "Total MG", "Total EG", "Tapered"
+    );
+    println!("{}", line);
+    for (name, [white, black]) in trace.terms() {
+        println!(
+            "{:<12} | {:>8} {:>8} | {:>8} {:>8} | {:>8} {:>8} | {:>7}",
+            name,
+            white[0],
+            white[1],
+            black[0],
+            black[1],
+            white[0] - black[0],
+            white[1] - black[1],
+            trace.tapered([white, black])
+        );
+    }
+    println!("{}", line);
+    println!(
+        "phase: {} / {} (0 is a pure endgame)",
+        trace.phase, TOTALPHASE
+    );
+    let side_factor = [1, -1][pos.side_to_move];
+    println!(
+        "eval: {} (white), {} (side to move)",
+        trace.eval,
+        side_factor * trace.eval
+    );
+}
+
 pub fn output_move_and_score(m: u16, s: i16, eval: i16) {
     let score_type = match s {
         30000 => "hash move",
@@ -110,5 +286,11 @@ pub fn output_move_and_score(m: u16, s: i16, eval: i16) {
         0 => "quiet",
         _ => "history",
     };
-    println!("{}: {} ({}), eval: {}", u16_to_uci(&m), s, score_type, eval)
+    println!(
+        "{}: {} ({}), eval: {}",
+        u16_to_uci(&m, false),
+        s,
+        score_type,
+        eval
+    )
 }
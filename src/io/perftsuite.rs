@@ -0,0 +1,130 @@
+// Perft test suites, one position per line in the perftsuite.epd style
+// <fen> ;D1 <leaf count> ;D2 <leaf count> ...
+// blank lines and lines starting with '#' are skipped
+
+use super::errors::PerftSuiteError;
+use crate::position::perft::perft;
+use crate::position::zobrist::ZobristVals;
+use crate::position::Position;
+use std::fs;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A position and its expected leaf count at each listed depth
+pub struct PerftEntry {
+    pub fen: String,
+    pub expected: Vec<(u8, u64)>,
+}
+
+pub struct PerftMismatch {
+    pub fen: String,
+    pub depth: u8,
+    pub expected: u64,
+    pub found: u64,
+}
+
+#[derive(Default)]
+pub struct PerftSuiteReport {
+    pub positions: usize,
+    /// depths compared against their expected count
+    pub checked: usize,
+    /// depths skipped for exceeding the node limit
+    pub skipped: usize,
+    pub nodes: u64,
+    pub mismatches: Vec<PerftMismatch>,
+}
+
+impl PerftSuiteReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+fn parse_depth(field: &str) -> Option<(u8, u64)> {
+    let (depth, count) = field.trim().split_once(char::is_whitespace)?;
+    let depth = depth.strip_prefix('D')?.parse().ok()?;
+    Some((depth, count.trim().parse().ok()?))
+}
+
+/// parses a whole suite, checking that every fen is valid
+pub fn parse_perft_suite(text: &str) -> Result<Vec<PerftEntry>, PerftSuiteError> {
+    let zvals = Arc::new(ZobristVals::default());
+    let mut entries = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: String| PerftSuiteError::Line(idx + 1, reason);
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim().to_string();
+        if Position::from_fen(&fen, zvals.clone()).is_err() {
+            return Err(error(format!("invalid fen '{}'", fen)));
+        }
+        let expected = fields
+            .map(|field| {
+                parse_depth(field).ok_or_else(|| error(format!("invalid depth '{}'", field)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if expected.is_empty() {
+            return Err(error(String::from("no depths given")));
+        }
+        entries.push(PerftEntry { fen, expected });
+    }
+    Ok(entries)
+}
+
+pub fn load_perft_suite(path: &str) -> Result<Vec<PerftEntry>, PerftSuiteError> {
+    parse_perft_suite(&fs::read_to_string(path)?)
+}
+
+/// checks every depth whose expected leaf count is at most max_nodes,
+/// printing the divide of each mismatch
+pub fn run_perft_suite(entries: &[PerftEntry], max_nodes: u64) -> PerftSuiteReport {
+    let zvals = Arc::new(ZobristVals::default());
+    let mut report = PerftSuiteReport::default();
+    let now = Instant::now();
+    for entry in entries {
+        let mut pos = Position::from_fen(&entry.fen, zvals.clone()).unwrap();
+        report.positions += 1;
+        let mut failed = false;
+        for &(depth, expected) in &entry.expected {
+            if expected > max_nodes {
+                report.skipped += 1;
+                continue;
+            }
+            let found = perft::<false, false>(&mut pos, depth);
+            report.checked += 1;
+            report.nodes += found;
+            if found != expected {
+                failed = true;
+                println!("{}", entry.fen);
+                println!(
+                    "depth {} expected {} found {}, divide:",
+                    depth, expected, found
+                );
+                perft::<true, false>(&mut pos, depth);
+                report.mismatches.push(PerftMismatch {
+                    fen: entry.fen.clone(),
+                    depth,
+                    expected,
+                    found,
+                });
+            }
+        }
+        if !failed {
+            println!("ok: {}", entry.fen);
+        }
+    }
+    let elapsed = now.elapsed().as_micros();
+    println!(
+        "{} positions, {} depths checked, {} skipped, {} failed, {} nodes ({:.2} ML/sec)",
+        report.positions,
+        report.checked,
+        report.skipped,
+        report.mismatches.len(),
+        report.nodes,
+        report.nodes as f64 / elapsed.max(1) as f64
+    );
+    report
+}
@@ -0,0 +1,511 @@
+// Portable game notation
+// games are read one at a time, so large collections never need to fit in memory
+// comments and NAGs are skipped, variations are kept as side lines of the move they replace
+// games are written in export format, with search scores as [%eval] comments
+
+use super::errors::PgnError;
+use crate::position::{zobrist::ZobristVals, Position};
+use crate::search::result::Score;
+use std::io::BufRead;
+use std::sync::Arc;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// written first, in this order, with '?' for unknown values
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+const LINE_LENGTH: usize = 79;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// game in progress, abandoned or result unknown
+    Unknown,
+}
+
+impl GameResult {
+    pub fn from_pgn(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn to_pgn(self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        }
+    }
+}
+
+/// A side line, played instead of a move of the line it belongs to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variation {
+    /// index of the replaced move in the enclosing line
+    pub ply: usize,
+    pub moves: Vec<u16>,
+    /// variations within this one
+    pub variations: Vec<Variation>,
+}
+
+/// A game that can be replayed from its starting position
+pub struct PgnGame {
+    /// tag pairs, in file order
+    pub tags: Vec<(String, String)>,
+    /// from the FEN tag if given, otherwise the start position
+    pub start: Position,
+    pub moves: Vec<u16>,
+    /// search score for each move from the mover's point of view, if known
+    /// written as [%eval] comments, empty for games read from pgn
+    pub scores: Vec<Option<Score>>,
+    /// variations of the main line, in file order
+    pub variations: Vec<Variation>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    /// a game with no tags or moves yet
+    pub fn new(start: Position) -> Self {
+        Self {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+            scores: Vec::new(),
+            variations: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// replaces the tag if already present
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// position after every move has been played
+    pub fn final_position(&self) -> Position {
+        let mut pos = self.start.clone();
+        for &m in &self.moves {
+            pos.make_move(m);
+        }
+        pos
+    }
+}
+
+/// a line being read, the main line or a variation
+struct Line {
+    /// position after the moves read so far
+    board: Position,
+    /// index of the move this line replaces in the enclosing line
+    ply: usize,
+    moves: Vec<u16>,
+    variations: Vec<Variation>,
+}
+
+/// game under construction, the board is set up when the movetext starts
+#[derive(Default)]
+struct GameBuilder {
+    tags: Vec<(String, String)>,
+    start: Option<Position>,
+    /// the main line, then any (variations) still open, innermost last
+    lines: Vec<Line>,
+    result: Option<GameResult>,
+    /// inside a {comment}, which may span lines
+    comment: bool,
+}
+
+impl GameBuilder {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.start.is_none()
+    }
+
+    /// inside a variation, which must be closed before the game ends
+    fn in_variation(&self) -> bool {
+        self.lines.len() > 1
+    }
+
+    /// the innermost open line
+    fn line(&mut self, line: usize, zvals: &Arc<ZobristVals>) -> Result<&mut Line, PgnError> {
+        if self.lines.is_empty() {
+            let fen = self
+                .tags
+                .iter()
+                .find(|(tag, _)| tag == "FEN")
+                .map_or(STARTPOS, |(_, fen)| fen.as_str());
+            let pos = Position::from_fen(fen, zvals.clone())
+                .map_err(|_| PgnError::Fen(line, fen.to_string()))?;
+            self.start = Some(pos.clone());
+            self.lines.push(Line {
+                board: pos,
+                ply: 0,
+                moves: Vec::new(),
+                variations: Vec::new(),
+            });
+        }
+        Ok(self.lines.last_mut().unwrap())
+    }
+
+    /// '(' starts a variation on the position before the last move
+    fn open_variation(&mut self, line: usize, zvals: &Arc<ZobristVals>) -> Result<(), PgnError> {
+        let current = self.line(line, zvals)?;
+        let ply = current
+            .moves
+            .len()
+            .checked_sub(1)
+            .ok_or(PgnError::Variation(line, "variation before any move"))?;
+        let mut board = current.board.clone();
+        board.unmake_move();
+        self.lines.push(Line {
+            board,
+            ply,
+            moves: Vec::new(),
+            variations: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn close_variation(&mut self, line: usize) -> Result<(), PgnError> {
+        if !self.in_variation() {
+            return Err(PgnError::Variation(line, "unmatched ')'"));
+        }
+        let closed = self.lines.pop().unwrap();
+        self.lines.last_mut().unwrap().variations.push(Variation {
+            ply: closed.ply,
+            moves: closed.moves,
+            variations: closed.variations,
+        });
+        Ok(())
+    }
+
+    fn build(mut self, line: usize, zvals: &Arc<ZobristVals>) -> Result<PgnGame, PgnError> {
+        if self.in_variation() {
+            return Err(PgnError::Variation(line, "unclosed '('"));
+        }
+        let main = self.line(line, zvals)?;
+        let (moves, variations) = (
+            std::mem::take(&mut main.moves),
+            std::mem::take(&mut main.variations),
+        );
+        Ok(PgnGame {
+            tags: self.tags,
+            start: self.start.unwrap(),
+            moves,
+            scores: Vec::new(),
+            variations,
+            result: self.result.unwrap_or(GameResult::Unknown),
+        })
+    }
+}
+
+/// `[Name "value"]`, with \" and \\ escapes in the value
+fn parse_tag(text: &str) -> Option<(String, String)> {
+    let inner = text.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        unescaped.push(if ch == '\\' { chars.next()? } else { ch });
+    }
+    Some((name.to_string(), unescaped))
+}
+
+/// Reads games from a pgn source one at a time. After an error the rest
+/// of the failed game is skipped, so reading can carry on with the next one
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: usize,
+    /// first line of the next game, read while looking for the end of the last
+    pending: Option<String>,
+    /// skipping the rest of a game that failed, true once past its tags
+    recovering: Option<bool>,
+    zvals: Arc<ZobristVals>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            pending: None,
+            recovering: None,
+            zvals: Arc::new(ZobristVals::default()),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, PgnError> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        if self.line == 1 {
+            line = line.trim_start_matches('\u{feff}').to_string();
+        }
+        Ok(Some(line))
+    }
+
+    /// handles one movetext token, returning true once the game's result is read
+    fn token(&self, game: &mut GameBuilder, token: &str) -> Result<bool, PgnError> {
+        if let Some(result) = GameResult::from_pgn(token) {
+            if game.in_variation() {
+                return Err(PgnError::Variation(self.line, "unclosed '('"));
+            }
+            game.result = Some(result);
+            return Ok(true);
+        }
+        // move numbers, possibly attached to the move as in 1.e4
+        let token = match token.rfind('.') {
+            Some(idx) if token.starts_with(|ch: char| ch.is_ascii_digit()) => &token[idx + 1..],
+            _ => token,
+        };
+        // NAGs and standalone annotations
+        if token.is_empty() || token.starts_with('$') || token.chars().all(|ch| "!?".contains(ch)) {
+            return Ok(false);
+        }
+
+        let line = self.line;
+        let current = game.line(line, &self.zvals)?;
+        let board = &mut current.board;
+        let m = board.parse_san(token).map_err(|error| PgnError::Move {
+            line,
+            move_number: format!(
+                "{}{}",
+                board.fullmove_counter,
+                if board.side_to_move == 0 { "." } else { "..." }
+            ),
+            error,
+        })?;
+        board.make_move(m);
+        current.moves.push(m);
+        Ok(false)
+    }
+
+    /// tokenises one line of movetext, returning true once the game's result is read
+    fn movetext(&self, game: &mut GameBuilder, text: &str) -> Result<bool, PgnError> {
+        let mut token_start = None;
+        for (idx, ch) in text.char_indices() {
+            if game.comment {
+                game.comment = ch != '}';
+                continue;
+            }
+            if !(ch.is_whitespace() || "{}();".contains(ch)) {
+                token_start.get_or_insert(idx);
+                continue;
+            }
+            if let Some(start) = token_start.take() {
+                if self.token(game, &text[start..idx])? {
+                    return Ok(true);
+                }
+            }
+            match ch {
+                '{' => game.comment = true,
+                '(' => game.open_variation(self.line, &self.zvals)?,
+                ')' => game.close_variation(self.line)?,
+                // the rest of the line is a comment
+                ';' => return Ok(false),
+                _ => (),
+            }
+        }
+        match token_start {
+            Some(start) => self.token(game, &text[start..]),
+            None => Ok(false),
+        }
+    }
+
+    fn next_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        let mut game = GameBuilder::default();
+        while let Some(line) = self.read_line()? {
+            let text = line.trim();
+            // escaped lines are for other software
+            if line.starts_with('%') && !game.comment {
+                continue;
+            }
+            if let Some(past_tags) = self.recovering {
+                if !(past_tags && text.starts_with('[')) {
+                    self.recovering =
+                        Some(past_tags || !(text.is_empty() || text.starts_with('[')));
+                    continue;
+                }
+                self.recovering = None;
+            }
+            // a variation can't run past the end of its movetext
+            if game.in_variation() && !game.comment && (text.is_empty() || text.starts_with('[')) {
+                // the next game's tags are read once the error has been reported
+                if text.starts_with('[') {
+                    self.pending = Some(line);
+                }
+                return Err(PgnError::Variation(self.line, "unclosed '('"));
+            }
+            if text.starts_with('[') && !game.comment {
+                if game.start.is_some() {
+                    // the last game ended without a result
+                    self.pending = Some(line);
+                    return game.build(self.line, &self.zvals).map(Some);
+                }
+                let tag =
+                    parse_tag(text).ok_or_else(|| PgnError::Tag(self.line, text.to_string()))?;
+                game.tags.push(tag);
+                continue;
+            }
+            if !text.is_empty() {
+                game.line(self.line, &self.zvals)?;
+            }
+            if self.movetext(&mut game, text)? {
+                return game.build(self.line, &self.zvals).map(Some);
+            }
+        }
+        if game.is_empty() {
+            return Ok(None);
+        }
+        game.build(self.line, &self.zvals).map(Some)
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_game() {
+            Ok(game) => game.map(Ok),
+            Err(err) => {
+                self.recovering = Some(!matches!(err, PgnError::Tag(..)));
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// every game in a pgn string
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    PgnReader::new(text.as_bytes()).collect()
+}
+
+/// `[%eval]` comment, in pawns from white's point of view
+fn eval_comment(score: Score, side_to_move: usize) -> String {
+    let sign = if side_to_move == 0 { 1 } else { -1 };
+    match score {
+        Score::Cp(cp) => format!("{{[%eval {:.2}]}}", (sign * cp as i32) as f64 / 100.0),
+        Score::Mate(moves) => format!("{{[%eval #{}]}}", sign * moves as i32),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// movetext of a line, each variation following the move it replaces
+/// the move number is repeated for black after a comment or variation
+fn line_tokens(
+    tokens: &mut Vec<String>,
+    mut pos: Position,
+    moves: &[u16],
+    scores: &[Option<Score>],
+    variations: &[Variation],
+) {
+    let mut after_comment = true;
+    for (idx, &m) in moves.iter().enumerate() {
+        let side = pos.side_to_move;
+        if side == 0 {
+            tokens.push(format!("{}.", pos.fullmove_counter));
+        } else if after_comment {
+            tokens.push(format!("{}...", pos.fullmove_counter));
+        }
+        tokens.push(pos.move_to_san(m));
+        after_comment = false;
+        if let Some(&Some(score)) = scores.get(idx) {
+            tokens.push(eval_comment(score, side));
+            after_comment = true;
+        }
+        for variation in variations
+            .iter()
+            .filter(|variation| variation.ply == idx && !variation.moves.is_empty())
+        {
+            let first = tokens.len();
+            line_tokens(
+                tokens,
+                pos.clone(),
+                &variation.moves,
+                &[],
+                &variation.variations,
+            );
+            tokens[first].insert(0, '(');
+            tokens.last_mut().unwrap().push(')');
+            after_comment = true;
+        }
+        pos.make_move(m);
+    }
+}
+
+impl PgnGame {
+    /// export format pgn, ending in a blank line
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let mut tag_line = |name: &str, value: &str| {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        };
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.to_pgn(),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            tag_line(name, value);
+        }
+        let start_fen = self.start.to_fen();
+        if start_fen != STARTPOS {
+            tag_line("SetUp", "1");
+            tag_line("FEN", &start_fen);
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                tag_line(name, value);
+            }
+        }
+
+        let mut tokens = Vec::new();
+        line_tokens(
+            &mut tokens,
+            self.start.clone(),
+            &self.moves,
+            &self.scores,
+            &self.variations,
+        );
+        tokens.push(self.result.to_pgn().to_string());
+
+        pgn.push('\n');
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push_str("\n\n");
+        pgn
+    }
+}
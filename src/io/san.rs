@@ -0,0 +1,197 @@
+// Standard algebraic notation, e.g. Nbd7, exd5, O-O, e8=Q+
+// parsing is lenient, so long algebraic (Ng1f3, e2-e4), 0-0 castles,
+// promotions without '=' and trailing annotations are accepted too
+
+use super::errors::SanError;
+use super::inputs::sq_to_idx;
+use super::outputs::idx_to_sq;
+use crate::position::{MoveFlags, MoveList, MoveType, Piece, Position};
+
+const PIECE_CHARS: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+const PROMO_CHARS: [char; 4] = ['N', 'B', 'R', 'Q'];
+
+fn is_castle(m: u16) -> bool {
+    let flag = m & MoveFlags::ALL;
+    flag == MoveFlags::KS_CASTLE || flag == MoveFlags::QS_CASTLE
+}
+
+fn from_sq(m: u16) -> usize {
+    (m & 63) as usize
+}
+
+fn to_sq(m: u16) -> usize {
+    ((m >> 6) & 63) as usize
+}
+
+/// promotion piece, as a Piece index
+fn promo_piece(m: u16) -> Option<usize> {
+    (m & MoveFlags::KNIGHT_PROMO > 0).then_some(((m >> 12) & 0b11) as usize + Piece::KNIGHT)
+}
+
+impl Position {
+    fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::default();
+        self.gen_moves::<{ MoveType::ALL }>(&mut moves);
+        moves
+    }
+
+    /// '+' or '#' if the move gives check or mate
+    fn check_suffix(&self, m: u16) -> &'static str {
+        let mut pos = self.clone();
+        pos.make_move(m);
+        if !pos.is_in_check() {
+            ""
+        } else if pos.legal_moves().is_empty() {
+            "#"
+        } else {
+            "+"
+        }
+    }
+
+    /// standard algebraic notation of a legal move
+    pub fn move_to_san(&self, m: u16) -> String {
+        let flag = m & MoveFlags::ALL;
+        let suffix = self.check_suffix(m);
+        match flag {
+            MoveFlags::KS_CASTLE => return format!("O-O{suffix}"),
+            MoveFlags::QS_CASTLE => return format!("O-O-O{suffix}"),
+            _ => (),
+        }
+
+        let (from, to) = (from_sq(m), to_sq(m));
+        let piece = self.squares[from] as usize;
+        let capture = flag & MoveFlags::CAPTURE > 0;
+        let from_name = idx_to_sq(from as u16);
+        let mut san = String::new();
+        if piece == Piece::PAWN {
+            if capture {
+                san.push_str(&from_name[..1]);
+            }
+        } else {
+            san.push(PIECE_CHARS[piece]);
+            // other pieces of the same type that can reach the same square
+            let moves = self.legal_moves();
+            let others: Vec<usize> = (0..moves.len())
+                .map(|i| moves[i])
+                .filter(|&other| {
+                    !is_castle(other)
+                        && to_sq(other) == to
+                        && from_sq(other) != from
+                        && self.squares[from_sq(other)] as usize == piece
+                })
+                .map(from_sq)
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|&other| other & 7 != from & 7) {
+                    san.push_str(&from_name[..1]);
+                } else if others.iter().all(|&other| other >> 3 != from >> 3) {
+                    san.push_str(&from_name[1..]);
+                } else {
+                    san.push_str(&from_name);
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&idx_to_sq(to as u16));
+        if let Some(promo) = promo_piece(m) {
+            san.push('=');
+            san.push(PIECE_CHARS[promo]);
+        }
+        san.push_str(suffix);
+        san
+    }
+
+    /// the legal move given in standard algebraic notation
+    pub fn parse_san(&self, san: &str) -> Result<u16, SanError> {
+        let invalid = || SanError::Invalid(san.to_string());
+        let mut text = san
+            .trim()
+            .trim_end_matches(['+', '#', '!', '?'])
+            .trim_end_matches("e.p.")
+            .trim_end();
+        if !text.is_ascii() || text.is_empty() {
+            return Err(invalid());
+        }
+
+        let moves = self.legal_moves();
+        let candidates: Vec<u16> = match text {
+            "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+                let flag = if text.len() == 3 {
+                    MoveFlags::KS_CASTLE
+                } else {
+                    MoveFlags::QS_CASTLE
+                };
+                (0..moves.len())
+                    .map(|i| moves[i])
+                    .filter(|&m| m & MoveFlags::ALL == flag)
+                    .collect()
+            }
+            _ => {
+                // promotion, as e8=Q, e8Q or e8(Q)
+                let mut promo = None;
+                text = text.trim_end_matches(')');
+                if let Some(ch) = text.chars().last() {
+                    let upper = ch.to_ascii_uppercase();
+                    if let Some(idx) = PROMO_CHARS.iter().position(|&pc| pc == upper) {
+                        let rest = text[..text.len() - 1].trim_end_matches(['=', '(']);
+                        if rest.ends_with(|ch: char| ch.is_ascii_digit()) {
+                            promo = Some(idx + Piece::KNIGHT);
+                            text = rest;
+                        }
+                    }
+                }
+
+                // moving piece, pawns have no letter
+                let mut piece = Piece::PAWN;
+                if let Some(idx) = PIECE_CHARS.iter().position(|&pc| text.starts_with(pc)) {
+                    piece = idx;
+                    text = &text[1..];
+                }
+
+                // destination, then whatever disambiguates the origin
+                let squares: String = text
+                    .chars()
+                    .filter(|&ch| !matches!(ch, 'x' | '-' | ':'))
+                    .collect();
+                if squares.len() < 2 || squares.len() > 4 {
+                    return Err(invalid());
+                }
+                let (origin, dest) = squares.split_at(squares.len() - 2);
+                let to = sq_to_idx(dest).ok_or_else(invalid)? as usize;
+                let mut from_file = None;
+                let mut from_rank = None;
+                for ch in origin.chars() {
+                    match ch {
+                        'a'..='h' => from_file = Some(ch as usize - 'a' as usize),
+                        '1'..='8' => from_rank = Some(ch as usize - '1' as usize),
+                        _ => return Err(invalid()),
+                    }
+                }
+                // a pawn without a file given can only push
+                if piece == Piece::PAWN && from_file.is_none() {
+                    from_file = Some(to & 7);
+                }
+
+                (0..moves.len())
+                    .map(|i| moves[i])
+                    .filter(|&m| {
+                        let from = from_sq(m);
+                        !is_castle(m)
+                            && to_sq(m) == to
+                            && self.squares[from] as usize == piece
+                            && from_file.is_none_or(|file| from & 7 == file)
+                            && from_rank.is_none_or(|rank| from >> 3 == rank)
+                            && promo_piece(m) == promo
+                    })
+                    .collect()
+            }
+        };
+        match candidates[..] {
+            [m] => Ok(m),
+            [] => Err(SanError::Illegal(san.to_string())),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
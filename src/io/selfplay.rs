@@ -0,0 +1,383 @@
+// In-process self-play between two engine configurations
+// each opening is played twice with colours reversed, and the pair results
+// feed an SPRT which stops the match early once a bound is crossed
+
+use super::pgn::{GameResult, PgnGame};
+use super::sprt::{elo_estimate, Pentanomial, Sprt, SprtOutcome};
+use crate::position::zobrist::ZobristVals;
+use crate::position::{MoveList, MoveType, Position};
+use crate::search::builder::{valid_hash_size, SearchLimits};
+use crate::search::result::Score;
+use crate::search::timings::Times;
+use crate::search::Engine;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// One side of a match
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub name: String,
+    /// MB
+    pub hash_size: usize,
+    pub threads: usize,
+    /// fixed depth per move, instead of the clock
+    pub depth: Option<i8>,
+    /// fixed nodes per move, instead of the clock
+    pub nodes: Option<u64>,
+}
+
+impl EngineConfig {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            hash_size: 16,
+            threads: 1,
+            depth: None,
+            nodes: None,
+        }
+    }
+
+    /// applies comma separated options, e.g. `name=dev,hash=64,threads=2,depth=8,nodes=5000`
+    pub fn parse_options(mut self, options: &str) -> Result<Self, String> {
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("invalid engine option '{option}'"))?;
+            let invalid = || format!("invalid value for {key}: '{value}'");
+            match key {
+                "name" => self.name = value.to_string(),
+                "hash" => self.hash_size = valid_hash_size(value)?,
+                "threads" => self.threads = value.parse().map_err(|_| invalid())?,
+                "depth" => self.depth = Some(value.parse().map_err(|_| invalid())?),
+                "nodes" => self.nodes = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(format!("unknown engine option '{key}'")),
+            }
+        }
+        Ok(self)
+    }
+
+    fn build(&self) -> Engine {
+        Engine::builder()
+            .hash_size(self.hash_size)
+            .threads(self.threads)
+            .build()
+    }
+
+    fn limits(&self, times: &Times) -> SearchLimits {
+        let fixed = self.depth.is_some() || self.nodes.is_some();
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            times: if fixed {
+                Times::default()
+            } else {
+                times.clone()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Base time and increment per move, in milliseconds
+#[derive(Clone, Copy, Debug)]
+pub struct TimeControl {
+    pub base: u64,
+    pub increment: u64,
+}
+
+impl TimeControl {
+    /// `base+increment` in seconds, e.g. `10+0.1`
+    pub fn parse(tc: &str) -> Option<Self> {
+        let (base, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+        let millis = |secs: &str| {
+            secs.parse::<f64>()
+                .ok()
+                .filter(|&s| s >= 0.0)
+                .map(|s| (s * 1000.0) as u64)
+        };
+        Some(Self {
+            base: millis(base)?,
+            increment: millis(increment)?,
+        })
+    }
+}
+
+/// Ending games early on the engines' scores
+#[derive(Clone, Copy, Debug)]
+pub struct Adjudication {
+    /// a side is lost once both engines score it at least this far behind...
+    pub resign_score: i16,
+    /// ...for this many consecutive moves each
+    pub resign_moves: usize,
+    /// a game is drawn once both engines score it within this...
+    pub draw_score: i16,
+    /// ...for this many consecutive moves each...
+    pub draw_moves: usize,
+    /// ...from this move number onwards
+    pub draw_move_number: u16,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: 1000,
+            resign_moves: 3,
+            draw_score: 10,
+            draw_moves: 8,
+            draw_move_number: 40,
+        }
+    }
+}
+
+pub struct MatchConfig {
+    pub engines: [EngineConfig; 2],
+    pub time_control: TimeControl,
+    /// starting fens, each played once with either colour
+    pub openings: Vec<String>,
+    /// the openings are reused if there are fewer of them
+    pub max_pairs: usize,
+    pub sprt: Option<Sprt>,
+    pub adjudication: Adjudication,
+    /// every game is appended here
+    pub pgn_file: Option<String>,
+}
+
+/// Results from the first engine's point of view
+#[derive(Default)]
+pub struct MatchReport {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+    pub pentanomial: Pentanomial,
+    pub outcome: Option<SprtOutcome>,
+}
+
+/// reads the positions of an epd or fen file, with move counters optional
+pub fn load_openings(path: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("couldn't read '{path}': {err}"))?;
+    let zvals = Arc::new(ZobristVals::default());
+    let mut openings = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let counters = fields.len() >= 6 && fields[4..6].iter().all(|f| f.parse::<u16>().is_ok());
+        let fen = if counters {
+            fields[..6].join(" ")
+        } else {
+            format!("{} 0 1", fields[..fields.len().min(4)].join(" "))
+        };
+        Position::from_fen(&fen, zvals.clone()).map_err(|_| format!("invalid opening '{line}'"))?;
+        openings.push(fen);
+    }
+    if openings.is_empty() {
+        return Err(format!("no openings in '{path}'"));
+    }
+    Ok(openings)
+}
+
+/// result and reason, if the game is over by the rules
+fn game_over(pos: &Position) -> Option<(GameResult, &'static str)> {
+    let mut moves = MoveList::default();
+    pos.gen_moves::<{ MoveType::ALL }>(&mut moves);
+    if moves.is_empty() {
+        if !pos.is_in_check() {
+            return Some((GameResult::Draw, "stalemate"));
+        }
+        let result = [GameResult::BlackWins, GameResult::WhiteWins][pos.side_to_move];
+        return Some((result, "checkmate"));
+    }
+    if pos.is_draw_by_repetition(3) {
+        return Some((GameResult::Draw, "threefold repetition"));
+    }
+    if pos.is_draw_by_50() {
+        return Some((GameResult::Draw, "fifty move rule"));
+    }
+    if pos.is_draw_by_material() {
+        return Some((GameResult::Draw, "insufficient material"));
+    }
+    None
+}
+
+/// score from white's point of view, with mates as large scores
+fn white_score(score: Score, side: usize) -> i32 {
+    let score = match score {
+        Score::Cp(cp) => cp as i32,
+        Score::Mate(moves) => 30000 * moves.signum() as i32,
+    };
+    if side == 0 {
+        score
+    } else {
+        -score
+    }
+}
+
+/// plays a game, engines[white] as white, returning it with the reason it ended
+fn play_game(
+    engines: &mut [Engine; 2],
+    configs: &[EngineConfig; 2],
+    white: usize,
+    opening: &str,
+    config: &MatchConfig,
+) -> (PgnGame, &'static str) {
+    let mut pos = Position::from_fen(opening, engines[0].zobrist_vals()).unwrap();
+    let mut game = PgnGame::new(pos.clone());
+    game.set_tag("Event", "Kimbo self-play");
+    game.set_tag("White", &configs[white].name);
+    game.set_tag("Black", &configs[white ^ 1].name);
+    for engine in engines.iter_mut() {
+        engine.new_game();
+    }
+
+    let tc = config.time_control;
+    let adj = config.adjudication;
+    let mut clocks = [tc.base; 2];
+    // consecutive plies for adjudication, both engines must agree
+    let (mut resign_plies, mut resign_sign, mut draw_plies) = (0, 0, 0);
+    let (result, reason) = loop {
+        if let Some(over) = game_over(&pos) {
+            break over;
+        }
+        let side = pos.side_to_move;
+        let player = white ^ side;
+        let times = Times {
+            wtime: clocks[0],
+            btime: clocks[1],
+            winc: tc.increment,
+            binc: tc.increment,
+            moves_to_go: None,
+        };
+        let engine = &mut engines[player];
+        engine.set_position(pos.clone());
+        let now = Instant::now();
+        let search = engine.search(&configs[player].limits(&times));
+        let elapsed = now.elapsed().as_millis() as u64;
+
+        if configs[player].depth.is_none() && configs[player].nodes.is_none() {
+            if elapsed > clocks[side] {
+                break (
+                    [GameResult::BlackWins, GameResult::WhiteWins][side],
+                    "loss on time",
+                );
+            }
+            clocks[side] = clocks[side] - elapsed + tc.increment;
+        }
+        pos.make_move(search.best_move);
+        game.moves.push(search.best_move);
+        game.scores.push(Some(search.score));
+
+        let score = white_score(search.score, side);
+        if score.abs() < adj.resign_score as i32 || score.signum() != resign_sign {
+            resign_plies = 0;
+            resign_sign = score.signum();
+        }
+        resign_plies += (score.abs() >= adj.resign_score as i32) as usize;
+        if resign_plies >= 2 * adj.resign_moves {
+            let winner = if resign_sign > 0 {
+                GameResult::WhiteWins
+            } else {
+                GameResult::BlackWins
+            };
+            break (winner, "adjudicated win");
+        }
+        if pos.fullmove_counter >= adj.draw_move_number && score.abs() <= adj.draw_score as i32 {
+            draw_plies += 1;
+        } else {
+            draw_plies = 0;
+        }
+        if draw_plies >= 2 * adj.draw_moves {
+            break (GameResult::Draw, "adjudicated draw");
+        }
+    };
+    game.result = result;
+    (game, reason)
+}
+
+/// points for the first engine, doubled
+fn half_points(result: GameResult, first_is_white: bool) -> usize {
+    match (result, first_is_white) {
+        (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => 2,
+        (GameResult::Draw, _) | (GameResult::Unknown, _) => 1,
+        _ => 0,
+    }
+}
+
+fn report_progress(report: &MatchReport, config: &MatchConfig) {
+    let games = report.wins + report.draws + report.losses;
+    let (elo, error) = elo_estimate(&report.pentanomial);
+    println!(
+        "Games: {games} W: {} D: {} L: {} Pentanomial: {:?}",
+        report.wins, report.draws, report.losses, report.pentanomial.0
+    );
+    print!("Elo: {:.2} +/- {:.2}", elo, error);
+    if let Some(sprt) = config.sprt {
+        print!(
+            " LLR: {:.2} ({:.2}, {:.2}) [{}, {}]",
+            sprt.llr(&report.pentanomial),
+            sprt.lower_bound(),
+            sprt.upper_bound(),
+            sprt.elo0,
+            sprt.elo1
+        );
+    }
+    println!();
+}
+
+/// plays game pairs until max_pairs is reached or the SPRT finishes
+pub fn run_match(config: &MatchConfig) -> Result<MatchReport, String> {
+    if config.openings.is_empty() {
+        return Err(String::from("no openings to play"));
+    }
+    let configs = &config.engines;
+    let mut engines = [configs[0].build(), configs[1].build()];
+    let mut report = MatchReport::default();
+    let mut game_number = 0;
+    for pair in 0..config.max_pairs {
+        let opening = &config.openings[pair % config.openings.len()];
+        let mut pair_points = 0;
+        for first_is_white in [true, false] {
+            let white = (!first_is_white) as usize;
+            let (mut game, reason) = play_game(&mut engines, configs, white, opening, config);
+            game_number += 1;
+            game.set_tag("Round", &game_number.to_string());
+            println!(
+                "Game {game_number}: {} vs {} {} ({reason})",
+                configs[white].name,
+                configs[white ^ 1].name,
+                game.result.to_pgn()
+            );
+            let points = half_points(game.result, first_is_white);
+            match points {
+                2 => report.wins += 1,
+                1 => report.draws += 1,
+                _ => report.losses += 1,
+            }
+            pair_points += points;
+            if let Some(path) = &config.pgn_file {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| file.write_all(game.to_pgn().as_bytes()))
+                    .map_err(|err| format!("couldn't write '{path}': {err}"))?;
+            }
+        }
+        report.pentanomial.add_pair(pair_points);
+        report_progress(&report, config);
+
+        if let Some(sprt) = config.sprt {
+            let outcome = sprt.outcome(&report.pentanomial);
+            report.outcome = Some(outcome);
+            if outcome != SprtOutcome::Continue {
+                break;
+            }
+        }
+    }
+    match report.outcome {
+        Some(SprtOutcome::AcceptH0) => println!("SPRT: H0 accepted"),
+        Some(SprtOutcome::AcceptH1) => println!("SPRT: H1 accepted"),
+        Some(SprtOutcome::Continue) => println!("SPRT: inconclusive"),
+        None => (),
+    }
+    Ok(report)
+}
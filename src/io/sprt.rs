@@ -0,0 +1,109 @@
+// Sequential probability ratio test on game pairs
+// uses the normalised GSPRT approximation of the log-likelihood ratio,
+// with the logistic elo model and pentanomial (game pair) results,
+// so the correlation between the two games of a pair is accounted for
+
+/// Game pair results for the first engine, indexed by points scored in the pair
+/// so [0, 0.5, 1, 1.5, 2] points, i.e. [LL, LD, DD or WL, WD, WW]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pentanomial(pub [u64; 5]);
+
+impl Pentanomial {
+    /// records a pair, given the first engine's points doubled (0 to 4)
+    pub fn add_pair(&mut self, half_points: usize) {
+        self.0[half_points] += 1;
+    }
+
+    pub fn pairs(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    /// mean and variance of the per-game score in a pair
+    fn mean_variance(&self) -> (f64, f64) {
+        let pairs = self.pairs() as f64;
+        let probs = self.0.map(|count| count as f64 / pairs);
+        let mean: f64 = (0..5).map(|i| probs[i] * i as f64 / 4.0).sum();
+        let variance = (0..5)
+            .map(|i| probs[i] * (i as f64 / 4.0 - mean).powi(2))
+            .sum();
+        (mean, variance)
+    }
+}
+
+/// expected score for an elo difference
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+pub fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// elo difference and its 95% confidence half-width
+pub fn elo_estimate(results: &Pentanomial) -> (f64, f64) {
+    if results.pairs() == 0 {
+        return (0.0, 0.0);
+    }
+    let (mean, variance) = results.mean_variance();
+    let margin = 1.96 * (variance / results.pairs() as f64).sqrt();
+    let elo = score_to_elo(mean);
+    let error = (score_to_elo(mean + margin) - score_to_elo(mean - margin)) / 2.0;
+    (elo, error)
+}
+
+/// H0: elo = elo0, H1: elo = elo1, with false positive rate alpha
+/// and false negative rate beta
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtOutcome {
+    /// the change is no better than elo0
+    AcceptH0,
+    /// the change is at least elo1 better
+    AcceptH1,
+    Continue,
+}
+
+impl Sprt {
+    /// H0 is accepted once the llr falls below this
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1.0 - self.alpha)).ln()
+    }
+
+    /// H1 is accepted once the llr rises above this
+    pub fn upper_bound(&self) -> f64 {
+        ((1.0 - self.beta) / self.alpha).ln()
+    }
+
+    /// log-likelihood ratio of H1 against H0
+    pub fn llr(&self, results: &Pentanomial) -> f64 {
+        if results.pairs() == 0 {
+            return 0.0;
+        }
+        let (mean, variance) = results.mean_variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let s0 = elo_to_score(self.elo0);
+        let s1 = elo_to_score(self.elo1);
+        results.pairs() as f64 * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn outcome(&self, results: &Pentanomial) -> SprtOutcome {
+        let llr = self.llr(results);
+        if llr <= self.lower_bound() {
+            SprtOutcome::AcceptH0
+        } else if llr >= self.upper_bound() {
+            SprtOutcome::AcceptH1
+        } else {
+            SprtOutcome::Continue
+        }
+    }
+}
@@ -1,18 +1,18 @@
 // Tokens enum inspired by Rustic
 // SOURCE: https://github.com/mvanthoor/rustic/blob/master/src/comm/uci.rs
 
+use super::bench::{bench as run_bench, BENCH_DEPTH};
 use super::errors::UciError;
 use super::info::*;
-use super::inputs::uci_to_u16;
-use crate::io::outputs::{display_board, report_stats, u16_to_uci};
-use crate::position::perft::perft;
+use super::inputs::{read_input, uci_to_u16};
+use crate::io::outputs::{
+    display_board, display_eval, display_perft_stats, report_stats, UciObserver,
+};
+use crate::position::perft::parallel_perft;
 use crate::position::zobrist::ZobristVals;
 use crate::position::Position;
-use crate::search::timings::Times;
+use crate::search::builder::{valid_hash_size, SearchLimits, MAX_HASH_SIZE};
 use crate::search::Engine;
-use crate::tables::pawn::PawnHashTable;
-use crate::tables::search::HashTable;
-use std::io;
 use std::process;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -24,26 +24,46 @@ use std::time::Instant;
 
 struct State {
     pos: Position,
-    search_handle: Option<JoinHandle<()>>,
+    /// None while a search thread has it
+    engine: Option<Engine>,
+    /// the search thread hands the engine back when joined
+    search_handle: Option<JoinHandle<Engine>>,
     stop: Arc<AtomicBool>,
-    ttable_size: usize,
-    ttable: Arc<HashTable>,
-    ptable: Arc<PawnHashTable>,
+    ponder: Arc<AtomicBool>,
+    infinite: bool,
     zvals: Arc<ZobristVals>,
-    move_overhead: u64,
+    /// UCI_Chess960, castles are read and written king-takes-rook
+    chess960: bool,
+}
+
+impl State {
+    /// replaces an engine lost to a panicked search, the stop and ponder
+    /// flags must be the new engine's for later searches to see them
+    fn restart_engine(&mut self) {
+        let mut engine = State::default().engine.unwrap();
+        engine.observer = Arc::new(UciObserver {
+            chess960: self.chess960,
+        });
+        self.stop = engine.stop_handle();
+        self.ponder = engine.ponder_handle();
+        self.engine = Some(engine);
+    }
 }
 
 impl Default for State {
     fn default() -> Self {
+        let engine = Engine::builder()
+            .observer(Arc::new(UciObserver::default()))
+            .build();
         State {
             pos: Position::default(),
+            stop: engine.stop_handle(),
+            ponder: engine.ponder_handle(),
+            zvals: engine.zobrist_vals(),
+            engine: Some(engine),
             search_handle: None,
-            stop: Arc::new(AtomicBool::new(false)),
-            ttable_size: 1,
-            ttable: Arc::new(HashTable::new(1024 * 1024)),
-            ptable: Arc::new(PawnHashTable::new(4 * 1024 * 1024)),
-            zvals: Arc::new(ZobristVals::default()),
-            move_overhead: 10,
+            infinite: false,
+            chess960: false,
         }
     }
 }
@@ -52,16 +72,26 @@ impl Default for State {
 pub fn uci_run() {
     println!("id name Kimbo {}", VERSION);
     println!("id author {}", AUTHOR);
-    println!("option name Hash type spin default 128 min 1 max 512");
+    println!("option name Hash type spin default 128 min 1 max {MAX_HASH_SIZE}");
     println!("option name Clear Hash type button");
     println!("option name Move Overhead type spin default 10 min 0 max 500");
+    println!("option name Threads type spin default 1 min 1 max 128");
+    println!("option name Ponder type check default false");
+    println!("option name MultiPV type spin default 1 min 1 max 64");
+    println!("option name UCI_Chess960 type check default false");
+    println!("option name UCI_ShowWDL type check default false");
     println!("uciok");
     let state: Arc<Mutex<State>> = Arc::new(Mutex::new(State::default()));
 
     'uci: loop {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let commands: Vec<&str> = input.split(' ').map(|v| v.trim()).collect();
+        let input = match read_input() {
+            Some(input) => input,
+            None => end_of_input(state),
+        };
+        let commands: Vec<&str> = input.split_whitespace().collect();
+        if commands.is_empty() {
+            continue;
+        }
         let leave = run_commands(state.clone(), commands);
         if leave {
             break 'uci;
@@ -74,18 +104,21 @@ fn run_commands(state: Arc<Mutex<State>>, commands: Vec<&str>) -> bool {
         // standard uci commands
         "go" => go(state, commands),
         "isready" => isready(),
+        "ponderhit" => ponderhit(state),
         "position" => position(state, commands),
         "ucinewgame" => ucinewgame(state),
         "setoption" => setoption(state, commands),
         "stop" => stop(state),
-        "quit" => process::exit(0),
+        "quit" => quit(state),
         // custom commands
         "display" => display(state, commands),
+        "bench" => bench(state, commands),
+        "eval" => eval(state),
         "break" => return true,
-        _ => return false,
+        command => Err(UciError::Unknown(command.to_string())),
     };
-    if result.is_err() {
-        println!("{}", result.unwrap_err())
+    if let Err(err) = result {
+        println!("info string {}", err)
     }
     false
 }
@@ -95,15 +128,83 @@ fn stop(state: Arc<Mutex<State>>) -> Result<(), UciError> {
     Ok(())
 }
 
+fn quit(state: Arc<Mutex<State>>) -> ! {
+    state.lock().unwrap().stop.store(true, Ordering::Relaxed);
+    process::exit(0)
+}
+
+/// closed input: lets a bounded search finish, stops an unbounded one, then quits
+fn end_of_input(state: Arc<Mutex<State>>) -> ! {
+    let state_lock = state.lock().unwrap();
+    let unbounded = state_lock.infinite || state_lock.ponder.load(Ordering::Relaxed);
+    drop(state_lock);
+    if unbounded {
+        finish_search(&state);
+    } else {
+        join_search(&state);
+    }
+    quit(state)
+}
+
+/// waits for any running search, and takes the engine back from it
+fn join_search(state: &Arc<Mutex<State>>) {
+    let handle = state.lock().unwrap().search_handle.take();
+    // lock must be released while joining, the search thread may print through it
+    if let Some(handle) = handle {
+        let engine = handle.join();
+        let mut state_lock = state.lock().unwrap();
+        match engine {
+            Ok(engine) => state_lock.engine = Some(engine),
+            Err(_) => state_lock.restart_engine(),
+        }
+    }
+}
+
+/// stops any running search and waits for it to report its bestmove
+fn finish_search(state: &Arc<Mutex<State>>) {
+    state.lock().unwrap().stop.store(true, Ordering::Relaxed);
+    join_search(state);
+}
+
+/// runs f on the engine, once any running search has been stopped
+fn with_engine<T>(state: &Arc<Mutex<State>>, f: impl FnOnce(&mut Engine) -> T) -> T {
+    finish_search(state);
+    f(state.lock().unwrap().engine.as_mut().unwrap())
+}
+
+fn eval(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    display_eval(&state.lock().unwrap().pos);
+    Ok(())
+}
+
+fn bench(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    let depth = match commands.get(1) {
+        Some(depth) => depth.parse::<i8>()?,
+        None => BENCH_DEPTH,
+    };
+    finish_search(&state);
+    run_bench(depth);
+    Ok(())
+}
+
+fn ponderhit(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    state.lock().unwrap().ponder.store(false, Ordering::Relaxed);
+    Ok(())
+}
+
 fn isready() -> Result<(), UciError> {
     println!("readyok");
     Ok(())
 }
 
 fn ucinewgame(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    with_engine(&state, |engine| engine.new_game());
     let mut state_lock = state.lock().unwrap();
-    state_lock.pos = Position::default();
-    state_lock.ttable.clear();
+    state_lock.pos = Position::from_fen(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        state_lock.zvals.clone(),
+    )
+    .unwrap();
     drop(state_lock);
     Ok(())
 }
@@ -135,15 +236,15 @@ fn display(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError
 }
 
 fn position(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
-    let mut state_lock = state.lock().unwrap();
     enum Tokens {
         Nothing,
         Fen,
         Moves,
     }
     let mut fen = String::from("");
-    let mut moves: Vec<String> = Vec::new();
+    let mut moves: Vec<&str> = Vec::new();
     let mut skip_fen = false;
+    let mut fen_given = false;
     let mut token = Tokens::Nothing;
 
     for command in commands {
@@ -151,44 +252,51 @@ fn position(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciErro
             "position" => (),
             "startpos" => {
                 skip_fen = true;
-                state_lock.pos = Position::from_fen(
-                    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
-                    state_lock.zvals.clone(),
-                )
-                .unwrap();
+                fen = String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
             }
             "fen" => {
+                fen_given = true;
                 if !skip_fen {
                     token = Tokens::Fen
                 }
             }
             "moves" => token = Tokens::Moves,
             _ => match token {
-                Tokens::Nothing => return Err(UciError::Position),
+                Tokens::Nothing => return Err(UciError::Position(command.to_string())),
                 Tokens::Fen => {
                     fen.push_str(command);
                     fen.push(' ');
                 }
-                Tokens::Moves => moves.push(command.to_string()),
+                Tokens::Moves => moves.push(command),
             },
         }
     }
 
-    if !fen.is_empty() && !skip_fen {
-        state_lock.pos = Position::from_fen(&fen, state_lock.zvals.clone())?;
+    // a position must be given, 'fen' must be followed by one
+    if !skip_fen && fen.is_empty() {
+        let token = if fen_given { "fen" } else { "position" };
+        return Err(UciError::Position(token.to_string()));
     }
+
+    // built on a scratch position, so the current one survives any bad token
+    let (zvals, chess960) = {
+        let state_lock = state.lock().unwrap();
+        (state_lock.zvals.clone(), state_lock.chess960)
+    };
+    let mut pos =
+        Position::from_fen(&fen, zvals).map_err(|_| UciError::Fen(fen.trim_end().to_string()))?;
     for m in moves {
-        let mo = uci_to_u16(&state_lock.pos, &m)?;
-        state_lock.pos.make_move(mo);
+        let mo = uci_to_u16(&pos, m, chess960)?;
+        pos.make_move(mo);
     }
-    drop(state_lock);
+    state.lock().unwrap().pos = pos;
     Ok(())
 }
 
 fn go(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
     #[derive(PartialEq)]
     enum Tokens {
-        Ponder,
+        None,
         Depth,
         Nodes,
         MoveTime,
@@ -197,105 +305,130 @@ fn go(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
         WInc,
         BInc,
         MovesToGo,
+        SearchMoves,
         Perft,
+        PerftThreads,
+        PerftHash,
     }
 
-    let state_lock = state.lock().unwrap();
-    state_lock.stop.store(false, Ordering::Relaxed);
-    drop(state_lock);
+    finish_search(&state);
 
     // fields to be set
-    let mut token = Tokens::Ponder;
-    let mut max_depth: i8 = i8::MAX;
-    let mut max_move_time: u64 = u64::MAX;
-    let mut max_nodes: u64 = u64::MAX;
-    let mut times: Times = Times::default();
+    let mut token = Tokens::None;
+    let mut limits = SearchLimits::default();
     let mut do_perft = false;
     let mut perft_depth = 0;
+    let mut perft_threads = 1;
+    let mut perft_hash = 0;
+    let mut perft_stats = false;
 
+    // whether the last keyword has its value, if it takes one
+    let mut given = true;
     for command in commands {
-        match command {
-            "go" => token = Tokens::Ponder,
-            "ponder" => token = Tokens::Ponder,
-            "depth" => token = Tokens::Depth,
-            "nodes" => token = Tokens::Nodes,
-            "movetime" => token = Tokens::MoveTime,
-            "wtime" => token = Tokens::WTime,
-            "btime" => token = Tokens::BTime,
-            "winc" => token = Tokens::WInc,
-            "binc" => token = Tokens::BInc,
-            "movestogo" => token = Tokens::MovesToGo,
+        let next = match command {
+            "go" => Tokens::None,
+            "ponder" => {
+                limits.ponder = true;
+                Tokens::None
+            }
+            "infinite" => {
+                limits.infinite = true;
+                Tokens::None
+            }
+            "depth" => Tokens::Depth,
+            "nodes" => Tokens::Nodes,
+            "movetime" => Tokens::MoveTime,
+            "wtime" => Tokens::WTime,
+            "btime" => Tokens::BTime,
+            "winc" => Tokens::WInc,
+            "binc" => Tokens::BInc,
+            "movestogo" => Tokens::MovesToGo,
+            "searchmoves" => Tokens::SearchMoves,
             "perft" => {
-                token = {
-                    do_perft = true;
-                    Tokens::Perft
+                do_perft = true;
+                Tokens::Perft
+            }
+            "threads" if do_perft => Tokens::PerftThreads,
+            "hash" if do_perft => Tokens::PerftHash,
+            "stats" if do_perft => {
+                perft_stats = true;
+                Tokens::None
+            }
+            _ => {
+                let times = &mut limits.times;
+                match token {
+                    Tokens::None => return Err(UciError::Go),
+                    Tokens::Depth => limits.depth = Some(command.parse::<i8>()?.max(1)),
+                    Tokens::Nodes => limits.nodes = Some(command.parse::<u64>()?),
+                    Tokens::MoveTime => limits.move_time = Some(command.parse::<u64>()?),
+                    Tokens::WTime => times.wtime = std::cmp::max(command.parse::<i64>()?, 0) as u64,
+                    Tokens::BTime => times.btime = std::cmp::max(command.parse::<i64>()?, 0) as u64,
+                    Tokens::WInc => times.winc = command.parse::<u64>()?,
+                    Tokens::BInc => times.binc = command.parse::<u64>()?,
+                    Tokens::MovesToGo => times.moves_to_go = Some(command.parse::<u8>()?.max(1)),
+                    Tokens::SearchMoves => {
+                        let state_lock = state.lock().unwrap();
+                        let m = uci_to_u16(&state_lock.pos, command, state_lock.chess960)?;
+                        limits.search_moves.push(m)
+                    }
+                    Tokens::Perft => perft_depth = command.parse::<u8>()?,
+                    Tokens::PerftThreads => perft_threads = command.parse::<usize>()?,
+                    Tokens::PerftHash => {
+                        perft_hash = valid_hash_size(command).map_err(UciError::Range)?
+                    }
                 }
+                given = true;
+                continue;
             }
-            _ => match token {
-                Tokens::Ponder => return Err(UciError::Go),
-                Tokens::Depth => max_depth = command.parse::<i8>()?,
-                Tokens::Nodes => max_nodes = command.parse::<u64>()?,
-                Tokens::MoveTime => max_move_time = command.parse::<u64>()?,
-                Tokens::WTime => times.wtime = std::cmp::max(command.parse::<i64>()?, 0) as u64,
-                Tokens::BTime => times.btime = std::cmp::max(command.parse::<i64>()?, 0) as u64,
-                Tokens::WInc => times.winc = command.parse::<u64>()?,
-                Tokens::BInc => times.binc = command.parse::<u64>()?,
-                Tokens::MovesToGo => times.moves_to_go = Some(command.parse::<u8>()?),
-                Tokens::Perft => perft_depth = command.parse::<u8>()?,
-            },
+        };
+        if !given {
+            return Err(UciError::Go);
         }
+        given = next == Tokens::None;
+        token = next;
     }
-
-    if !times.is_default() {
-        let state_lock = state.lock().unwrap();
-        max_move_time = times.to_movetime(state_lock.pos.side_to_move, state_lock.pos.phase);
-        drop(state_lock);
+    if !given {
+        return Err(UciError::Go);
     }
 
-    if do_perft {
-        let state_2 = state.clone();
-        let search_thread = thread::spawn(move || {
-            let state_lock = state_2.lock().unwrap();
-            let mut position = state_lock.pos.clone();
-            drop(state_lock);
+    // the engine and position are taken now, a later 'position' must not leak into this search
+    let mut state_lock = state.lock().unwrap();
+    state_lock.infinite = limits.infinite;
+    let chess960 = state_lock.chess960;
+    let mut engine = state_lock.engine.take().unwrap();
+    engine.set_position(state_lock.pos.clone());
+    engine.set_limits(&limits);
+    drop(state_lock);
 
+    let search_thread = if perft_stats {
+        thread::spawn(move || {
+            display_perft_stats(&engine.board, perft_depth);
+            engine
+        })
+    } else if do_perft {
+        thread::spawn(move || {
             let now = Instant::now();
-            let count = perft::<true, false>(&mut position, perft_depth);
+            let count = parallel_perft(
+                &engine.board,
+                perft_depth,
+                perft_threads,
+                perft_hash,
+                chess960,
+            );
             let elapsed = now.elapsed().as_micros();
             println!(
                 "Leaf count: {count} ({:.2} ML/sec)",
                 count as f64 / elapsed as f64
             );
-        });
-        state.lock().unwrap().search_handle = Some(search_thread);
-        return Ok(());
-    }
-
-    // SEARCHING ON SECOND THREAD
-    let state_2 = state.clone();
-    let search_thread = thread::spawn(move || {
-        let state_lock = state_2.lock().unwrap();
-        let position = state_lock.pos.clone();
-        let abort_signal = state_lock.stop.clone();
-        let tt = state_lock.ttable.clone();
-        let pt = state_lock.ptable.clone();
-        let move_overhead = state_lock.move_overhead;
-        drop(state_lock);
-
-        let move_time = max_move_time - move_overhead * (max_move_time > move_overhead) as u64;
-
-        let mut search = Engine::new(
-            position,
-            abort_signal,
-            move_time,
-            max_depth,
-            max_nodes,
-            tt,
-            pt,
-        );
-        let best_move = search.go::<true>();
-        println!("bestmove {}", u16_to_uci(&best_move));
-    });
+            engine
+        })
+    } else {
+        // SEARCHING ON SECOND THREAD
+        thread::spawn(move || {
+            engine.go_smp(engine.threads);
+            engine
+        })
+    };
     // join handle provided to master thread
     state.lock().unwrap().search_handle = Some(search_thread);
     Ok(())
@@ -327,24 +460,36 @@ fn setoption(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciErr
             }
         }
     }
+    let value = || value_token.first().copied().ok_or(UciError::SetOption);
     match name_token.join(" ").as_str() {
         "Hash" => {
-            let size = value_token[0].parse::<usize>()?;
-            let mut state_lock = state.lock().unwrap();
-            state_lock.ttable_size = std::cmp::max(1, size as i16 - 4) as usize;
-            state_lock.ttable = Arc::new(HashTable::new(state_lock.ttable_size * 1024 * 1024));
-            drop(state_lock)
+            let size = value()?.parse::<usize>()?;
+            with_engine(&state, |engine| engine.set_memory(size))
         }
-        "Clear Hash" => {
-            let state_lock = state.lock().unwrap();
-            state_lock.ttable.clear();
-            drop(state_lock)
+        "Ponder" => (),
+        "UCI_Chess960" => {
+            let chess960 = value()? == "true";
+            with_engine(&state, |engine| {
+                engine.observer = Arc::new(UciObserver { chess960 })
+            });
+            state.lock().unwrap().chess960 = chess960;
+        }
+        "UCI_ShowWDL" => {
+            let show_wdl = value()? == "true";
+            with_engine(&state, |engine| engine.show_wdl = show_wdl)
         }
+        "MultiPV" => {
+            let lines = value()?.parse::<usize>()?;
+            with_engine(&state, |engine| engine.multipv = lines.clamp(1, 64))
+        }
+        "Clear Hash" => with_engine(&state, |engine| engine.ttable.clear()),
         "Move Overhead" => {
-            let size = value_token[0].parse::<u64>()?;
-            let mut state_lock = state.lock().unwrap();
-            state_lock.move_overhead = size;
-            drop(state_lock)
+            let move_overhead = value()?.parse::<u64>()?;
+            with_engine(&state, |engine| engine.move_overhead = move_overhead)
+        }
+        "Threads" => {
+            let threads = value()?.parse::<usize>()?;
+            with_engine(&state, |engine| engine.set_threads(threads.clamp(1, 128)))
         }
         _ => return Err(UciError::SetOption),
     }
@@ -0,0 +1,442 @@
+// xboard / CECP protocol
+// SOURCE: https://www.gnu.org/software/xboard/engine-intf.html
+
+use super::errors::UciError;
+use super::info::*;
+use super::inputs::{read_input, sq_to_idx, uci_to_u16};
+use crate::io::outputs::{u16_to_uci, XboardObserver};
+use crate::position::zobrist::ZobristVals;
+use crate::position::{MoveList, MoveType, Position, Side};
+use crate::search::builder::SearchLimits;
+use crate::search::observer::SilentObserver;
+use crate::search::timings::Times;
+use crate::search::Engine;
+use std::process;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::thread::JoinHandle;
+
+struct State {
+    pos: Position,
+    /// None while a search thread has it
+    engine: Option<Engine>,
+    /// the search thread hands the engine back when joined
+    search_handle: Option<JoinHandle<Engine>>,
+    stop: Arc<AtomicBool>,
+    /// set when a search is abandoned, so its move is not played
+    cancelled: bool,
+    zvals: Arc<ZobristVals>,
+    /// in force mode the engine only records moves
+    force: bool,
+    engine_side: usize,
+    post: bool,
+    max_depth: i8,
+    /// fixed time per move, set by 'st'
+    move_time: Option<u64>,
+    /// moves per time control, 0 for the whole game
+    moves_per_session: u16,
+    increment: u64,
+    time: u64,
+    otim: u64,
+}
+
+impl State {
+    /// replaces an engine lost to a panicked search, the stop flag
+    /// must be the new engine's for later searches to see it
+    fn restart_engine(&mut self) {
+        let engine = State::default().engine.unwrap();
+        self.stop = engine.stop_handle();
+        self.engine = Some(engine);
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let engine = Engine::builder().build();
+        let zvals = engine.zobrist_vals();
+        State {
+            pos: Position::from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                zvals.clone(),
+            )
+            .unwrap(),
+            stop: engine.stop_handle(),
+            engine: Some(engine),
+            search_handle: None,
+            cancelled: false,
+            zvals,
+            force: false,
+            engine_side: Side::BLACK,
+            post: false,
+            max_depth: i8::MAX,
+            move_time: None,
+            moves_per_session: 0,
+            increment: 0,
+            time: 0,
+            otim: 0,
+        }
+    }
+}
+
+/// runs the xboard loop
+pub fn xboard_run() {
+    println!();
+    let state: Arc<Mutex<State>> = Arc::new(Mutex::new(State::default()));
+
+    'xboard: loop {
+        let input = match read_input() {
+            Some(input) => input,
+            None => quit(state),
+        };
+        let commands: Vec<&str> = input.split_whitespace().collect();
+        if commands.is_empty() {
+            continue;
+        }
+        let leave = run_commands(state.clone(), commands);
+        if leave {
+            break 'xboard;
+        }
+    }
+}
+
+fn run_commands(state: Arc<Mutex<State>>, commands: Vec<&str>) -> bool {
+    let line = commands.join(" ");
+    // refused moves are explained using the position they were tried in
+    let state_2 = state.clone();
+    let result = match commands[0] {
+        "protover" => protover(),
+        "new" => new(state),
+        "force" => force(state),
+        "go" => go(state),
+        "playother" => playother(state),
+        "usermove" => match commands.get(1) {
+            Some(m) => usermove(state, m),
+            None => Err(UciError::Value),
+        },
+        "level" => level(state, commands),
+        "st" => st(state, commands),
+        "sd" => sd(state, commands),
+        "time" => clock::<true>(state, commands),
+        "otim" => clock::<false>(state, commands),
+        "undo" => undo(state, 1),
+        "remove" => undo(state, 2),
+        "setboard" => setboard(state, commands),
+        "post" => post(state, true),
+        "nopost" => post(state, false),
+        "result" => force(state),
+        "memory" => memory(state, commands),
+        "ping" => ping(commands),
+        "?" => stop(state),
+        "quit" => quit(state),
+        // accepted but unused
+        "xboard" | "accepted" | "rejected" | "random" | "easy" | "hard" | "computer" | "name"
+        | "rating" | "ics" | "draw" | "hint" | "bk" | "variant" => Ok(()),
+        "break" => return true,
+        // moves are still accepted without the 'usermove' prefix
+        command => {
+            let is_move = uci_to_u16(&state.lock().unwrap().pos, command, false).is_ok();
+            if is_move {
+                usermove(state, command)
+            } else {
+                Err(UciError::Unknown(command.to_string()))
+            }
+        }
+    };
+    match result {
+        Ok(()) => (),
+        Err(UciError::Move(m)) => {
+            let reason = illegal_reason(&state_2.lock().unwrap().pos, &m);
+            println!("Illegal move ({}): {}", reason, m)
+        }
+        Err(UciError::Fen(_)) => println!("tellusererror Illegal position"),
+        Err(UciError::Unknown(command)) => println!("Error (unknown command): {}", command),
+        Err(_) => println!("Error (invalid argument): {}", line),
+    }
+    false
+}
+
+/// why a move was refused
+fn illegal_reason(pos: &Position, m: &str) -> &'static str {
+    let squares = match m.len() {
+        4 | 5 if m.is_ascii() => sq_to_idx(&m[0..2]).zip(sq_to_idx(&m[2..4])),
+        _ => None,
+    };
+    match squares {
+        None => "invalid notation",
+        Some((from, _)) if pos.sides[pos.side_to_move] & (1 << from) == 0 => {
+            "no piece of yours there"
+        }
+        Some(_) if pos.is_in_check() => "in check",
+        Some(_) => "piece can't move there",
+    }
+}
+
+fn protover() -> Result<(), UciError> {
+    println!(
+        "feature myname=\"Kimbo {}\" setboard=1 usermove=1 ping=1 playother=1 memory=1 \
+         colors=0 san=0 sigint=0 sigterm=0 reuse=1 analyze=0 done=1",
+        VERSION
+    );
+    Ok(())
+}
+
+fn quit(state: Arc<Mutex<State>>) -> ! {
+    state.lock().unwrap().stop.store(true, Ordering::Relaxed);
+    process::exit(0)
+}
+
+fn ping(commands: Vec<&str>) -> Result<(), UciError> {
+    println!("pong {}", commands.get(1).unwrap_or(&""));
+    Ok(())
+}
+
+/// moves now, the search's move is still played
+fn stop(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    state.lock().unwrap().stop.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// abandons any running search without playing its move
+fn cancel_search(state: &Arc<Mutex<State>>) {
+    let mut state_lock = state.lock().unwrap();
+    state_lock.cancelled = true;
+    state_lock.stop.store(true, Ordering::Relaxed);
+    let handle = state_lock.search_handle.take();
+    // lock must be released, the search thread takes it to play its move
+    drop(state_lock);
+    if let Some(handle) = handle {
+        let engine = handle.join();
+        let mut state_lock = state.lock().unwrap();
+        match engine {
+            Ok(engine) => state_lock.engine = Some(engine),
+            Err(_) => state_lock.restart_engine(),
+        }
+    }
+}
+
+fn new(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    let engine = state_lock.engine.as_mut().unwrap();
+    engine.new_game();
+    state_lock.pos = engine.board.clone();
+    state_lock.force = false;
+    state_lock.engine_side = Side::BLACK;
+    state_lock.max_depth = i8::MAX;
+    state_lock.move_time = None;
+    drop(state_lock);
+    Ok(())
+}
+
+fn force(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    cancel_search(&state);
+    state.lock().unwrap().force = true;
+    Ok(())
+}
+
+fn go(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    state_lock.force = false;
+    state_lock.engine_side = state_lock.pos.side_to_move;
+    drop(state_lock);
+    think(&state);
+    Ok(())
+}
+
+fn playother(state: Arc<Mutex<State>>) -> Result<(), UciError> {
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    state_lock.force = false;
+    state_lock.engine_side = state_lock.pos.side_to_move ^ 1;
+    drop(state_lock);
+    Ok(())
+}
+
+fn usermove(state: Arc<Mutex<State>>, m: &str) -> Result<(), UciError> {
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    let mo = uci_to_u16(&state_lock.pos, m, false)?;
+    state_lock.pos.make_move(mo);
+    let engine_to_move = !state_lock.force && state_lock.pos.side_to_move == state_lock.engine_side;
+    drop(state_lock);
+    if engine_to_move {
+        think(&state);
+    }
+    Ok(())
+}
+
+fn undo(state: Arc<Mutex<State>>, count: usize) -> Result<(), UciError> {
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    for _ in 0..count {
+        if state_lock.pos.state_stack.is_empty() {
+            break;
+        }
+        state_lock.pos.unmake_move();
+    }
+    drop(state_lock);
+    Ok(())
+}
+
+fn setboard(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    cancel_search(&state);
+    let fen = commands[1..].join(" ");
+    let mut state_lock = state.lock().unwrap();
+    state_lock.pos = Position::from_fen(&fen, state_lock.zvals.clone())
+        .map_err(|_| UciError::Fen(fen.clone()))?;
+    drop(state_lock);
+    Ok(())
+}
+
+fn post(state: Arc<Mutex<State>>, post: bool) -> Result<(), UciError> {
+    state.lock().unwrap().post = post;
+    Ok(())
+}
+
+/// level MPS BASE INC, with BASE in minutes or minutes:seconds and INC in seconds
+fn level(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    if commands.len() != 4 {
+        return Err(UciError::Value);
+    }
+    let moves_per_session = commands[1].parse::<u16>()?;
+    let base = match commands[2].split_once(':') {
+        Some((minutes, seconds)) => minutes.parse::<u64>()? * 60 + seconds.parse::<u64>()?,
+        None => commands[2].parse::<u64>()? * 60,
+    };
+    let increment = commands[3].parse::<f64>().map_err(|_| UciError::Value)?;
+    let mut state_lock = state.lock().unwrap();
+    state_lock.moves_per_session = moves_per_session;
+    // both clocks start at the base time, until told otherwise by 'time' and 'otim'
+    state_lock.time = base * 1000;
+    state_lock.otim = base * 1000;
+    state_lock.increment = (increment.max(0.0) * 1000.0) as u64;
+    state_lock.move_time = None;
+    drop(state_lock);
+    Ok(())
+}
+
+/// st TIME, exact time per move in seconds
+fn st(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    let seconds = commands.get(1).ok_or(UciError::Value)?.parse::<u64>()?;
+    state.lock().unwrap().move_time = Some(seconds * 1000);
+    Ok(())
+}
+
+fn sd(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    let depth = commands.get(1).ok_or(UciError::Value)?.parse::<i8>()?;
+    state.lock().unwrap().max_depth = depth.max(1);
+    Ok(())
+}
+
+/// time / otim N, remaining clock in centiseconds
+fn clock<const OWN: bool>(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    let centis = commands.get(1).ok_or(UciError::Value)?.parse::<i64>()?;
+    let millis = std::cmp::max(centis, 0) as u64 * 10;
+    let mut state_lock = state.lock().unwrap();
+    if OWN {
+        state_lock.time = millis;
+    } else {
+        state_lock.otim = millis;
+    }
+    drop(state_lock);
+    Ok(())
+}
+
+fn memory(state: Arc<Mutex<State>>, commands: Vec<&str>) -> Result<(), UciError> {
+    let size = commands.get(1).ok_or(UciError::Value)?.parse::<usize>()?;
+    cancel_search(&state);
+    let mut state_lock = state.lock().unwrap();
+    state_lock.engine.as_mut().unwrap().set_memory(size);
+    drop(state_lock);
+    Ok(())
+}
+
+/// the game result, if the game is over by the rules
+fn game_result(pos: &Position) -> Option<&'static str> {
+    let mut moves = MoveList::default();
+    pos.gen_moves::<{ MoveType::ALL }>(&mut moves);
+    if moves.is_empty() {
+        return Some(if !pos.is_in_check() {
+            "1/2-1/2 {Stalemate}"
+        } else if pos.side_to_move == Side::WHITE {
+            "0-1 {Black mates}"
+        } else {
+            "1-0 {White mates}"
+        });
+    }
+    if pos.is_draw_by_50() {
+        Some("1/2-1/2 {50 move rule}")
+    } else if pos.is_draw_by_repetition(3) {
+        Some("1/2-1/2 {Draw by repetition}")
+    } else if pos.is_draw_by_material() {
+        Some("1/2-1/2 {Insufficient material}")
+    } else {
+        None
+    }
+}
+
+/// searches on a second thread, which plays and sends its move unless cancelled
+fn think(state: &Arc<Mutex<State>>) {
+    let mut state_lock = state.lock().unwrap();
+    if let Some(result) = game_result(&state_lock.pos) {
+        println!("{}", result);
+        return;
+    }
+    state_lock.cancelled = false;
+    state_lock.stop.store(false, Ordering::Relaxed);
+
+    // moves left in this session, assuming the game started from move 1,
+    // fens may give a fullmove number of 0
+    let moves_to_go = match state_lock.moves_per_session {
+        0 => None,
+        mps => {
+            let played = (state_lock.pos.fullmove_counter.max(1) - 1) % mps;
+            Some((mps - played).min(u8::MAX as u16) as u8)
+        }
+    };
+    let (own, other) = (state_lock.time, state_lock.otim);
+    let (wtime, btime) = match state_lock.engine_side {
+        Side::WHITE => (own, other),
+        _ => (other, own),
+    };
+    let limits = SearchLimits {
+        depth: Some(state_lock.max_depth),
+        move_time: state_lock.move_time,
+        times: Times {
+            wtime,
+            btime,
+            winc: state_lock.increment,
+            binc: state_lock.increment,
+            moves_to_go,
+        },
+        ..Default::default()
+    };
+
+    let mut engine = state_lock.engine.take().unwrap();
+    engine.set_position(state_lock.pos.clone());
+    engine.set_limits(&limits);
+    engine.observer = match state_lock.post {
+        true => Arc::new(XboardObserver),
+        false => Arc::new(SilentObserver),
+    };
+    drop(state_lock);
+
+    let state_2 = state.clone();
+    let search_thread = thread::spawn(move || {
+        let best_move = engine.go_smp(engine.threads).best_move;
+        let mut state_lock = state_2.lock().unwrap();
+        if !state_lock.cancelled {
+            state_lock.pos.make_move(best_move);
+            println!("move {}", u16_to_uci(&best_move, false).trim_end());
+            if let Some(result) = game_result(&state_lock.pos) {
+                println!("{}", result);
+            }
+        }
+        engine
+    });
+    state.lock().unwrap().search_handle = Some(search_thread);
+}
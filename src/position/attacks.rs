@@ -8,8 +8,8 @@ pub const fn rook_attacks(idx: usize, occupied: u64) -> u64 {
     let masks = MASKS[idx];
     let mut forward = occupied & masks.file;
     let mut reverse = forward.swap_bytes();
-    forward -= masks.bitmask;
-    reverse -= masks.bitmask.swap_bytes();
+    forward = forward.wrapping_sub(masks.bitmask);
+    reverse = reverse.wrapping_sub(masks.bitmask.swap_bytes());
     forward ^= reverse.swap_bytes();
     forward &= masks.file;
 
@@ -30,15 +30,15 @@ pub const fn bishop_attacks(idx: usize, occ: u64) -> u64 {
     let masks = MASKS[idx];
     let mut forward = occ & masks.diag;
     let mut reverse = forward.swap_bytes();
-    forward -= masks.bitmask;
-    reverse -= masks.bitmask.swap_bytes();
+    forward = forward.wrapping_sub(masks.bitmask);
+    reverse = reverse.wrapping_sub(masks.bitmask.swap_bytes());
     forward ^= reverse.swap_bytes();
     forward &= masks.diag;
 
     let mut forward2 = occ & masks.antidiag;
     let mut reverse2 = forward2.swap_bytes();
-    forward2 -= masks.bitmask;
-    reverse2 -= masks.bitmask.swap_bytes();
+    forward2 = forward2.wrapping_sub(masks.bitmask);
+    reverse2 = reverse2.wrapping_sub(masks.bitmask.swap_bytes());
     forward2 ^= reverse2.swap_bytes();
     forward2 &= masks.antidiag;
 
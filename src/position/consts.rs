@@ -1,5 +1,3 @@
-use super::CastleRights;
-
 pub fn get_weight<const MG: bool>(idx: usize, side: usize, piece: usize) -> i16 {
     let indx = match side {
         0 => idx ^ 56,
@@ -978,9 +976,10 @@ pub const PAWN_ATTACKS: [[u64; 64]; 2] = [
 ];
 
 // for legal movegen
+#[allow(clippy::large_const_arrays)]
 pub const IN_BETWEEN: [[u64; 64]; 64] = in_between_lookup();
+#[allow(clippy::large_const_arrays)]
 pub const LINE_THROUGH: [[u64; 64]; 64] = line_through_lookup();
-pub const CASTLE_RIGHTS: [u8; 64] = castle_rights();
 
 // for hyperbola quintessence
 pub const MASKS: [Masks; 64] = masks();
@@ -1075,14 +1074,3 @@ const fn masks() -> [Masks; 64] {
     }
     masks
 }
-
-const fn castle_rights() -> [u8; 64] {
-    let mut rights = [CastleRights::ALL; 64];
-    rights[0] = 0b0111;
-    rights[7] = 0b1011;
-    rights[56] = 0b1101;
-    rights[63] = 0b1110;
-    rights[4] = 0b0011;
-    rights[60] = 0b1100;
-    rights
-}
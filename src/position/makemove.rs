@@ -1,5 +1,10 @@
 use super::consts::*;
-use super::{CastleRights, GameState, MoveFlags, Piece, Position, Side};
+use super::{castle_dests, CastleRights, GameState, MoveFlags, Piece, Position, Side};
+
+#[inline(always)]
+fn is_castle(flag: u16) -> bool {
+    flag == MoveFlags::KS_CASTLE || flag == MoveFlags::QS_CASTLE
+}
 
 impl Position {
     /// Makes a move on a position
@@ -7,8 +12,14 @@ impl Position {
         let opponent = self.side_to_move ^ 1;
         // extract move data
         let from_idx = (m & 63) as usize;
-        let to_idx = ((m >> 6) & 63) as usize;
+        let mut to_idx = ((m >> 6) & 63) as usize;
         let flag = m & MoveFlags::ALL;
+        // castles are encoded king-takes-rook
+        let rook_idx = to_idx;
+        let mut rook_to = to_idx;
+        if is_castle(flag) {
+            (to_idx, rook_to) = castle_dests(self.side_to_move, flag);
+        }
         // used derivates of move data
         let from = 1u64 << from_idx;
         let to = 1u64 << to_idx;
@@ -29,7 +40,8 @@ impl Position {
             pst_eg: self.pst_eg,
         };
         self.squares[from_idx] = Piece::NONE as u8;
-        let mo = from | to;
+        // xor so that a Chess960 king castling onto its own square stays put
+        let mo = from ^ to;
         // update fields
         self.pieces[self.side_to_move][moved_pc as usize] ^= mo;
         self.sides[self.side_to_move] ^= mo;
@@ -73,9 +85,9 @@ impl Position {
                 ctx.captured_pc = captured_pc as u8;
                 self.pieces[opponent][captured_pc] ^= to;
                 self.sides[opponent] ^= to;
-                self.squares[to_idx] = moved_pc as u8;
+                self.squares[to_idx] = moved_pc;
                 if captured_pc == Piece::ROOK {
-                    self.castle_rights &= CASTLE_RIGHTS[to_idx];
+                    self.castle_rights &= self.castle_mask(to_idx);
                 }
                 self.pst_mg[self.side_to_move] +=
                     get_weight::<true>(to_idx, self.side_to_move, moved_pc as usize);
@@ -159,76 +171,33 @@ impl Position {
                     self.zobrist_vals
                         .piece_hash(to_idx, self.side_to_move, moved_pc as usize);
             }
-            MoveFlags::QS_CASTLE => {
+            MoveFlags::QS_CASTLE | MoveFlags::KS_CASTLE => {
                 self.castle_rights &= CastleRights::SIDES[opponent];
-                let castle = match self.side_to_move {
-                    Side::WHITE => {
-                        self.squares[0] = Piece::NONE as u8;
-                        self.squares[3] = Piece::ROOK as u8;
-                        A1 | D1
-                    }
-                    Side::BLACK => {
-                        self.squares[56] = Piece::NONE as u8;
-                        self.squares[59] = Piece::ROOK as u8;
-                        A8 | D8
-                    }
-                    _ => panic!("Invalid side!"),
-                };
+                let castle = (1 << rook_idx) ^ (1 << rook_to);
                 self.pieces[self.side_to_move][Piece::ROOK] ^= castle;
                 self.sides[self.side_to_move] ^= castle;
+                // rook may land on the king's square or vice versa in Chess960
+                self.squares[rook_idx] = Piece::NONE as u8;
                 self.squares[to_idx] = Piece::KING as u8;
+                self.squares[rook_to] = Piece::ROOK as u8;
                 self.pst_mg[self.side_to_move] +=
                     get_weight::<true>(to_idx, self.side_to_move, moved_pc as usize);
                 self.pst_eg[self.side_to_move] +=
                     get_weight::<false>(to_idx, self.side_to_move, moved_pc as usize);
-                let (idx1, idx2) = match self.side_to_move {
-                    Side::WHITE => (0, 3),
-                    Side::BLACK => (56, 59),
-                    _ => panic!("Invalid side!"),
-                };
-                self.pst_mg[self.side_to_move] -= get_weight::<true>(idx1, self.side_to_move, 3);
-                self.pst_eg[self.side_to_move] -= get_weight::<false>(idx1, self.side_to_move, 3);
-                self.pst_mg[self.side_to_move] += get_weight::<true>(idx2, self.side_to_move, 3);
-                self.pst_eg[self.side_to_move] += get_weight::<false>(idx2, self.side_to_move, 3);
-                self.zobrist ^= self.zobrist_vals.piece_hash(idx1, self.side_to_move, 3);
-                self.zobrist ^= self.zobrist_vals.piece_hash(idx2, self.side_to_move, 3);
-                self.zobrist ^=
-                    self.zobrist_vals
-                        .piece_hash(to_idx, self.side_to_move, moved_pc as usize);
-            }
-            MoveFlags::KS_CASTLE => {
-                self.castle_rights &= CastleRights::SIDES[opponent];
-                let castle = match self.side_to_move {
-                    Side::WHITE => {
-                        self.squares[7] = Piece::NONE as u8;
-                        self.squares[5] = Piece::ROOK as u8;
-                        F1 | H1
-                    }
-                    Side::BLACK => {
-                        self.squares[63] = Piece::NONE as u8;
-                        self.squares[61] = Piece::ROOK as u8;
-                        F8 | H8
-                    }
-                    _ => panic!("Invalid side!"),
-                };
-                self.pieces[self.side_to_move][Piece::ROOK] ^= castle;
-                self.sides[self.side_to_move] ^= castle;
-                self.squares[to_idx] = Piece::KING as u8;
+                self.pst_mg[self.side_to_move] -=
+                    get_weight::<true>(rook_idx, self.side_to_move, Piece::ROOK);
+                self.pst_eg[self.side_to_move] -=
+                    get_weight::<false>(rook_idx, self.side_to_move, Piece::ROOK);
                 self.pst_mg[self.side_to_move] +=
-                    get_weight::<true>(to_idx, self.side_to_move, moved_pc as usize);
+                    get_weight::<true>(rook_to, self.side_to_move, Piece::ROOK);
                 self.pst_eg[self.side_to_move] +=
-                    get_weight::<false>(to_idx, self.side_to_move, moved_pc as usize);
-                let (idx1, idx2) = match self.side_to_move {
-                    Side::WHITE => (7, 5),
-                    Side::BLACK => (63, 61),
-                    _ => panic!("Invalid side!"),
-                };
-                self.pst_mg[self.side_to_move] -= get_weight::<true>(idx1, self.side_to_move, 3);
-                self.pst_eg[self.side_to_move] -= get_weight::<false>(idx1, self.side_to_move, 3);
-                self.pst_mg[self.side_to_move] += get_weight::<true>(idx2, self.side_to_move, 3);
-                self.pst_eg[self.side_to_move] += get_weight::<false>(idx2, self.side_to_move, 3);
-                self.zobrist ^= self.zobrist_vals.piece_hash(idx1, self.side_to_move, 3);
-                self.zobrist ^= self.zobrist_vals.piece_hash(idx2, self.side_to_move, 3);
+                    get_weight::<false>(rook_to, self.side_to_move, Piece::ROOK);
+                self.zobrist ^=
+                    self.zobrist_vals
+                        .piece_hash(rook_idx, self.side_to_move, Piece::ROOK);
+                self.zobrist ^=
+                    self.zobrist_vals
+                        .piece_hash(rook_to, self.side_to_move, Piece::ROOK);
                 self.zobrist ^=
                     self.zobrist_vals
                         .piece_hash(to_idx, self.side_to_move, moved_pc as usize);
@@ -265,7 +234,7 @@ impl Position {
                     self.sides[opponent] ^= to;
                     self.squares[to_idx] = promo_pc as u8;
                     if captured_pc == Piece::ROOK {
-                        self.castle_rights &= CASTLE_RIGHTS[to_idx];
+                        self.castle_rights &= self.castle_mask(to_idx);
                     }
                     self.pst_mg[self.side_to_move] +=
                         get_weight::<true>(to_idx, self.side_to_move, promo_pc);
@@ -301,10 +270,12 @@ impl Position {
         } else {
             self.halfmove_clock = 0
         }
-        if self.castle_rights > CastleRights::NONE
-            && (moved_pc == Piece::KING as u8 || moved_pc == Piece::ROOK as u8)
-        {
-            self.castle_rights &= CASTLE_RIGHTS[from_idx]
+        if self.castle_rights > CastleRights::NONE {
+            if moved_pc == Piece::KING as u8 {
+                self.castle_rights &= CastleRights::SIDES[opponent]
+            } else if moved_pc == Piece::ROOK as u8 {
+                self.castle_rights &= self.castle_mask(from_idx)
+            }
         }
         let mut changed_castle = ctx.castle_rights & !self.castle_rights;
         while changed_castle > 0 {
@@ -339,14 +310,19 @@ impl Position {
         self.side_to_move ^= 1;
         // extract move data
         let from_idx = (m & 63) as usize;
-        let to_idx = ((m >> 6) & 63) as usize;
+        let mut to_idx = ((m >> 6) & 63) as usize;
         let flag = m & MoveFlags::ALL;
+        let rook_idx = to_idx;
+        let mut rook_to = to_idx;
+        if is_castle(flag) {
+            (to_idx, rook_to) = castle_dests(self.side_to_move, flag);
+        }
         // used derivates of move data
         let from = 1u64 << from_idx;
         let to = 1u64 << to_idx;
         let moved_pc = ctx.moved_pc as usize;
         self.squares[from_idx] = moved_pc as u8;
-        let m = from | to;
+        let m = from ^ to;
         // reset fields
         self.pieces[self.side_to_move][moved_pc] ^= m;
         self.sides[self.side_to_move] ^= m;
@@ -364,7 +340,7 @@ impl Position {
             MoveFlags::CAPTURE => {
                 self.pieces[opponent][ctx.captured_pc as usize] ^= to;
                 self.sides[opponent] ^= to;
-                self.squares[to_idx] = ctx.captured_pc as u8;
+                self.squares[to_idx] = ctx.captured_pc;
             }
             MoveFlags::EN_PASSANT => {
                 let (pwn, pwn_idx) = match opponent {
@@ -376,41 +352,14 @@ impl Position {
                 self.sides[opponent] ^= pwn;
                 self.squares[pwn_idx] = Piece::PAWN as u8;
             }
-            MoveFlags::QS_CASTLE => {
-                let castle = match self.side_to_move {
-                    Side::WHITE => {
-                        self.squares[3] = Piece::NONE as u8;
-                        self.squares[0] = Piece::ROOK as u8;
-                        A1 | D1
-                    }
-                    Side::BLACK => {
-                        self.squares[59] = Piece::NONE as u8;
-                        self.squares[56] = Piece::ROOK as u8;
-                        A8 | D8
-                    }
-                    _ => panic!("Invalid side!"),
-                };
-                self.pieces[self.side_to_move][Piece::ROOK] ^= castle;
-                self.sides[self.side_to_move] ^= castle;
-                self.squares[to_idx] = Piece::NONE as u8;
-            }
-            MoveFlags::KS_CASTLE => {
-                let castle = match self.side_to_move {
-                    Side::WHITE => {
-                        self.squares[5] = Piece::NONE as u8;
-                        self.squares[7] = Piece::ROOK as u8;
-                        F1 | H1
-                    }
-                    Side::BLACK => {
-                        self.squares[61] = Piece::NONE as u8;
-                        self.squares[63] = Piece::ROOK as u8;
-                        F8 | H8
-                    }
-                    _ => panic!("Invalid side!"),
-                };
+            MoveFlags::QS_CASTLE | MoveFlags::KS_CASTLE => {
+                let castle = (1 << rook_idx) ^ (1 << rook_to);
                 self.pieces[self.side_to_move][Piece::ROOK] ^= castle;
                 self.sides[self.side_to_move] ^= castle;
                 self.squares[to_idx] = Piece::NONE as u8;
+                self.squares[rook_to] = Piece::NONE as u8;
+                self.squares[from_idx] = Piece::KING as u8;
+                self.squares[rook_idx] = Piece::ROOK as u8;
             }
             _ => {
                 // is a promotion
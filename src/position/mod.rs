@@ -32,6 +32,9 @@ pub struct Position {
     // heap allocated
     pub state_stack: Vec<GameState>,
     pub zobrist_vals: Arc<ZobristVals>,
+    /// Starting squares of the castling rooks, indexed as castle rights bits
+    /// (needed for Chess960)
+    pub castle_rooks: [u16; 4],
     /// Eval stuff
     pub mat_mg: [i16; 2],
     pub mat_eg: [i16; 2],
@@ -107,6 +110,54 @@ impl CastleRights {
         Self::BLACK_KS | Self::BLACK_QS,
     ];
     pub const NONE: u8 = 0;
+
+    /// castle right of the given side, for a castling move flag
+    #[inline(always)]
+    pub const fn right(side: usize, flag: u16) -> u8 {
+        let kingside = match side {
+            Side::WHITE => Self::WHITE_KS,
+            _ => Self::BLACK_KS,
+        };
+        if flag == MoveFlags::KS_CASTLE {
+            kingside
+        } else {
+            kingside << 1
+        }
+    }
+}
+
+/// Destination squares (king, rook) of a castling move
+#[inline(always)]
+pub const fn castle_dests(side: usize, flag: u16) -> (usize, usize) {
+    let rank = 56 * side;
+    if flag == MoveFlags::KS_CASTLE {
+        (rank + 6, rank + 5)
+    } else {
+        (rank + 2, rank + 3)
+    }
+}
+
+/// Standard starting squares of the castling rooks, indexed as castle rights bits
+pub const STANDARD_CASTLE_ROOKS: [u16; 4] = [63, 56, 7, 0];
+
+impl Position {
+    /// Starting square of the rook for the given castle right
+    #[inline(always)]
+    pub fn castle_rook(&self, right: u8) -> u16 {
+        self.castle_rooks[ls1b_scan(right as u64) as usize]
+    }
+
+    /// Castle rights that remain after a piece moves from, or is captured on, the given square
+    #[inline(always)]
+    pub fn castle_mask(&self, idx: usize) -> u8 {
+        let mut mask = CastleRights::ALL;
+        for (i, &sq) in self.castle_rooks.iter().enumerate() {
+            if sq as usize == idx {
+                mask &= !(1 << i);
+            }
+        }
+        mask
+    }
 }
 
 #[inline(always)]
@@ -213,6 +264,18 @@ impl MoveList {
             ptr::swap(ptr.add(i), ptr.add(j));
         }
     }
+
+    /// Keeps only the moves satisfying the predicate, preserving order
+    pub fn retain<F: Fn(u16) -> bool>(&mut self, f: F) {
+        let mut len = 0;
+        for i in 0..self.len {
+            if f(self.list[i]) {
+                self.list[len] = self.list[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
 }
 
 impl Index<usize> for MoveList {
@@ -254,44 +254,42 @@ impl Position {
         }
     }
 
+    /// castles are encoded king-takes-rook, so the same code handles Chess960
     fn castles<const MOVETYPE: u8>(&self, move_list: &mut MoveList) {
         if MOVETYPE == MoveType::CAPTURES {
             return;
         }
-        match self.side_to_move {
-            Side::WHITE => {
-                if self.castle_rights & CastleRights::WHITE_QS > 0
-                    && self.occupied & (B1 | C1 | D1) == 0
-                    && !self.is_square_attacked(3, Side::WHITE, self.occupied)
-                    && !self.is_square_attacked(2, Side::WHITE, self.occupied)
-                {
-                    move_list.push(MoveFlags::QS_CASTLE | 2 << 6 | 4)
-                }
-                if self.castle_rights & CastleRights::WHITE_KS > 0
-                    && self.occupied & (F1 | G1) == 0
-                    && !self.is_square_attacked(5, Side::WHITE, self.occupied)
-                    && !self.is_square_attacked(6, Side::WHITE, self.occupied)
-                {
-                    move_list.push(MoveFlags::KS_CASTLE | 6 << 6 | 4)
-                }
+        let side = self.side_to_move;
+        let king_idx = ls1b_scan(self.pieces[side][Piece::KING]) as usize;
+        for flag in [MoveFlags::QS_CASTLE, MoveFlags::KS_CASTLE] {
+            let right = CastleRights::right(side, flag);
+            if self.castle_rights & right == 0 {
+                continue;
             }
-            Side::BLACK => {
-                if self.castle_rights & CastleRights::BLACK_QS > 0
-                    && self.occupied & (B8 | C8 | D8) == 0
-                    && !self.is_square_attacked(59, Side::BLACK, self.occupied)
-                    && !self.is_square_attacked(58, Side::BLACK, self.occupied)
-                {
-                    move_list.push(MoveFlags::QS_CASTLE | 58 << 6 | 60)
-                }
-                if self.castle_rights & CastleRights::BLACK_KS > 0
-                    && self.occupied & (F8 | G8) == 0
-                    && !self.is_square_attacked(61, Side::BLACK, self.occupied)
-                    && !self.is_square_attacked(62, Side::BLACK, self.occupied)
-                {
-                    move_list.push(MoveFlags::KS_CASTLE | 62 << 6 | 60)
+            let rook_idx = self.castle_rook(right) as usize;
+            let (king_to, rook_to) = castle_dests(side, flag);
+            let rook = 1 << rook_idx;
+            // all squares travelled through must be empty, ignoring the king and rook
+            let occupied = self.occupied ^ rook ^ (1 << king_idx);
+            let king_path = IN_BETWEEN[king_idx][king_to] | (1 << king_to);
+            let rook_path = IN_BETWEEN[rook_idx][rook_to] | (1 << rook_to);
+            if occupied & (king_path | rook_path) > 0 {
+                continue;
+            }
+            // king may not pass through check, the rook might have been shielding it
+            let mut path = king_path;
+            let mut safe = true;
+            while path > 0 {
+                let idx = ls1b_scan(path) as usize;
+                if self.is_square_attacked(idx, side, self.occupied ^ rook) {
+                    safe = false;
+                    break;
                 }
+                path &= path - 1;
+            }
+            if safe {
+                move_list.push(flag | (rook_idx as u16) << 6 | king_idx as u16)
             }
-            _ => panic!("Invalid side for castling!"),
         }
     }
 
@@ -436,7 +434,7 @@ impl Position {
             }
             Check::Single => {
                 let idx = ls1b_scan(checks);
-                let free = IN_BETWEEN[king_idx as usize][idx as usize];
+                let free = IN_BETWEEN[king_idx][idx as usize];
                 let blockers = free | checks;
                 self.gen_pnbrq_moves::<true, MOVETYPE>(
                     move_list, pinned, king_idx, blockers, checks, free,
@@ -1,9 +1,12 @@
-use super::{MoveList, MoveType, Position};
+use super::{castle_dests, ls1b_scan, MoveFlags, MoveList, MoveType, Piece, Position};
 use crate::{
     eval::{calc_material, calc_pst},
     io::outputs::u16_to_uci,
     position::zobrist::{initialise_pawnhash, initialise_zobrist},
+    tables::perft::PerftHashTable,
 };
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
 
 pub fn perft<const ROOT: bool, const DEBUG: bool>(position: &mut Position, depth_left: u8) -> u64 {
     if DEBUG {
@@ -23,8 +26,8 @@ pub fn perft<const ROOT: bool, const DEBUG: bool>(position: &mut Position, depth
     let mut moves = MoveList::default();
     position.gen_moves::<{ MoveType::ALL }>(&mut moves);
 
-    // bulk counting on depth 1
-    if depth_left == 1 {
+    // bulk counting on depth 1, except at the root so every move is listed
+    if depth_left == 1 && !ROOT {
         return moves.len() as u64;
     }
 
@@ -45,8 +48,177 @@ pub fn perft<const ROOT: bool, const DEBUG: bool>(position: &mut Position, depth
 
         // print positions from this move if root
         if ROOT {
-            println!("{}: {}", u16_to_uci(&m), score);
+            println!("{}: {}", u16_to_uci(&m, false), score);
+        }
+    }
+    positions
+}
+
+/// perft with subtree counts cached in the table, worthwhile from depth 2 upwards
+fn hashed_perft(position: &mut Position, depth_left: u8, table: &PerftHashTable) -> u64 {
+    if depth_left == 0 {
+        return 1;
+    }
+
+    let mut moves = MoveList::default();
+    position.gen_moves::<{ MoveType::ALL }>(&mut moves);
+    if depth_left == 1 {
+        return moves.len() as u64;
+    }
+
+    if let Some(positions) = table.get(position.zobrist, depth_left) {
+        return positions;
+    }
+
+    let mut positions: u64 = 0;
+    for m_idx in 0..moves.len() {
+        position.make_move(moves[m_idx]);
+        positions += hashed_perft(position, depth_left - 1, table);
+        position.unmake_move();
+    }
+    table.push(position.zobrist, depth_left, positions);
+    positions
+}
+
+/// Root moves are shared out between threads, and with a non-zero hash size (MB)
+/// transpositions are counted once. Prints the same divide as perft::<true, false>,
+/// with castles written king-takes-rook in Chess960 mode
+pub fn parallel_perft(
+    position: &Position,
+    depth: u8,
+    threads: usize,
+    hash_size: usize,
+    chess960: bool,
+) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let table = (hash_size > 0).then(|| PerftHashTable::new(hash_size * 1024 * 1024));
+
+    let mut moves = MoveList::default();
+    position.gen_moves::<{ MoveType::ALL }>(&mut moves);
+    let counts: Vec<AtomicU64> = (0..moves.len()).map(|_| AtomicU64::new(0)).collect();
+    let next_move = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| {
+                let mut pos = position.clone();
+                loop {
+                    let m_idx = next_move.fetch_add(1, Ordering::Relaxed);
+                    if m_idx >= moves.len() {
+                        break;
+                    }
+                    pos.make_move(moves[m_idx]);
+                    let count = match &table {
+                        Some(table) => hashed_perft(&mut pos, depth - 1, table),
+                        None => perft::<false, false>(&mut pos, depth - 1),
+                    };
+                    pos.unmake_move();
+                    counts[m_idx].store(count, Ordering::Relaxed);
+                }
+            });
         }
+    });
+
+    let mut positions: u64 = 0;
+    for m_idx in 0..moves.len() {
+        let count = counts[m_idx].load(Ordering::Relaxed);
+        println!("{}: {}", u16_to_uci(&moves[m_idx], chess960), count);
+        positions += count;
     }
     positions
 }
+
+/// Leaf counts broken down by move class, as in the chessprogramming wiki perft tables
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    /// including en passant and capture promotions
+    pub captures: u64,
+    pub en_passant: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    /// single checks from a piece other than the one that moved,
+    /// double checks are only counted as such
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
+
+impl PerftStats {
+    fn add(&mut self, other: &Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovered_checks += other.discovered_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+
+    /// classifies the leaf reached by m, which has just been made
+    fn count_leaf(&mut self, position: &Position, m: u16, mover: usize) {
+        let flag = m & MoveFlags::ALL;
+        let mut to_idx = ((m >> 6) & 63) as usize;
+        self.nodes += 1;
+        match flag {
+            MoveFlags::EN_PASSANT => {
+                self.captures += 1;
+                self.en_passant += 1;
+            }
+            MoveFlags::KS_CASTLE | MoveFlags::QS_CASTLE => {
+                self.castles += 1;
+                // only the rook can give check
+                to_idx = castle_dests(mover, flag).1;
+            }
+            _ => {
+                self.captures += (flag & MoveFlags::CAPTURE > 0) as u64;
+                self.promotions += (flag & MoveFlags::KNIGHT_PROMO > 0) as u64;
+            }
+        }
+
+        let side = position.side_to_move;
+        let king_idx = ls1b_scan(position.pieces[side][Piece::KING]) as usize;
+        let (checkers, _) = position.checkers_pinned_pieces(side, king_idx);
+        if checkers == 0 {
+            return;
+        }
+        self.checks += 1;
+        if checkers.count_ones() > 1 {
+            self.double_checks += 1;
+        } else {
+            self.discovered_checks += (checkers & !(1 << to_idx) > 0) as u64;
+        }
+        let mut replies = MoveList::default();
+        position.gen_moves::<{ MoveType::ALL }>(&mut replies);
+        self.checkmates += replies.is_empty() as u64;
+    }
+}
+
+/// perft with every leaf classified, so a wrong count can be traced to a move type
+pub fn perft_stats(position: &mut Position, depth_left: u8) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth_left == 0 {
+        stats.nodes = 1;
+        return stats;
+    }
+
+    let mut moves = MoveList::default();
+    position.gen_moves::<{ MoveType::ALL }>(&mut moves);
+    let mover = position.side_to_move;
+    for m_idx in 0..moves.len() {
+        let m = moves[m_idx];
+        position.make_move(m);
+        if depth_left == 1 {
+            stats.count_leaf(position, m, mover);
+        } else {
+            stats.add(&perft_stats(position, depth_left - 1));
+        }
+        position.unmake_move();
+    }
+    stats
+}
@@ -0,0 +1,199 @@
+use super::observer::{SearchObserver, SilentObserver};
+use super::result::SearchResult;
+use super::timings::Times;
+use super::Engine;
+use crate::position::{zobrist::ZobristVals, Position};
+use crate::tables::{
+    countermove::CounterMoveTable, history::HistoryTable, killer::KillerMoveTable,
+    pawn::PawnHashTable, search::HashTable,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+const PAWN_HASH_SIZE: usize = 4 * 1024 * 1024;
+/// Largest hash table size in MB, also the maximum of the UCI Hash option
+pub const MAX_HASH_SIZE: usize = 512;
+
+/// Parses a hash size in MB, accepting the range of the UCI Hash option
+pub fn valid_hash_size(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|size| (1..=MAX_HASH_SIZE).contains(size))
+        .ok_or_else(|| format!("invalid hash size '{value}', must be 1 to {MAX_HASH_SIZE} MB"))
+}
+
+/// Limits for a single search, unlimited by default
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<i8>,
+    pub nodes: Option<u64>,
+    /// Fixed time per move in milliseconds, preferred over the clock
+    pub move_time: Option<u64>,
+    /// Clock times, ignored if infinite
+    pub times: Times,
+    /// Analysis mode, search until stopped
+    pub infinite: bool,
+    /// Start by pondering, the clock starts on ponderhit
+    pub ponder: bool,
+    /// Root moves to consider, all if empty
+    pub search_moves: Vec<u16>,
+}
+
+/// Builds a long-lived engine, which keeps its tables between searches
+pub struct EngineBuilder {
+    position: Option<Position>,
+    hash_size: usize,
+    threads: usize,
+    move_overhead: u64,
+    multipv: usize,
+    show_wdl: bool,
+    observer: Arc<dyn SearchObserver>,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self {
+            position: None,
+            hash_size: 1,
+            threads: 1,
+            move_overhead: 10,
+            multipv: 1,
+            show_wdl: false,
+            observer: Arc::new(SilentObserver),
+        }
+    }
+}
+
+impl EngineBuilder {
+    /// Starting position, the standard start position by default
+    pub fn position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+    /// Search hash table size in MB
+    pub fn hash_size(mut self, size: usize) -> Self {
+        self.hash_size = size.max(1);
+        self
+    }
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    /// Milliseconds kept back from every timed search
+    pub fn move_overhead(mut self, move_overhead: u64) -> Self {
+        self.move_overhead = move_overhead;
+        self
+    }
+    pub fn multipv(mut self, multipv: usize) -> Self {
+        self.multipv = multipv.max(1);
+        self
+    }
+    pub fn show_wdl(mut self, show_wdl: bool) -> Self {
+        self.show_wdl = show_wdl;
+        self
+    }
+    pub fn observer(mut self, observer: Arc<dyn SearchObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    pub fn build(self) -> Engine {
+        let mut engine = Engine::new(
+            self.position.unwrap_or_default(),
+            Arc::new(AtomicBool::new(false)),
+            u64::MAX,
+            i8::MAX,
+            u64::MAX,
+            Arc::new(HashTable::new(self.hash_size * 1024 * 1024)),
+            Arc::new(PawnHashTable::new(PAWN_HASH_SIZE)),
+        );
+        engine.threads = self.threads;
+        engine.move_overhead = self.move_overhead;
+        engine.multipv = self.multipv;
+        engine.show_wdl = self.show_wdl;
+        engine.observer = self.observer;
+        engine
+    }
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.board = position;
+    }
+
+    /// Zobrist values shared by positions searched with this engine
+    pub fn zobrist_vals(&self) -> Arc<ZobristVals> {
+        self.board.zobrist_vals.clone()
+    }
+
+    /// Setting the handle to true stops the current search, from any thread
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Setting the handle to false starts the clock of a ponder search (ponderhit)
+    pub fn ponder_handle(&self) -> Arc<AtomicBool> {
+        self.ponder.clone()
+    }
+
+    /// Forgets everything learned in previous searches and returns to the start position
+    pub fn new_game(&mut self) {
+        self.board = Position::from_fen(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            self.zobrist_vals(),
+        )
+        .unwrap();
+        self.ttable.clear();
+        self.ctable = Arc::new(CounterMoveTable::default());
+        self.ktable = Arc::new(KillerMoveTable::default());
+        self.htable = Arc::new(HistoryTable::default());
+    }
+
+    /// Replaces the search hash table with an empty one of the given size in MB
+    pub fn resize_hash(&mut self, size: usize) {
+        self.ttable = Arc::new(HashTable::new(size.max(1) * 1024 * 1024));
+    }
+
+    /// Sizes the hash tables to a memory budget in MB, as given by the UCI Hash
+    /// option or the xboard memory command, the pawn hash table taking its share
+    pub fn set_memory(&mut self, size: usize) {
+        let pawn_hash_size = PAWN_HASH_SIZE / (1024 * 1024);
+        self.resize_hash(size.clamp(1, MAX_HASH_SIZE).saturating_sub(pawn_hash_size));
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Applies the limits for the next search, and resets the stop and ponder signals
+    pub fn set_limits(&mut self, limits: &SearchLimits) {
+        let mut max_move_time = limits.move_time.unwrap_or(u64::MAX);
+        if limits.move_time.is_none() && !limits.times.is_default() && !limits.infinite {
+            max_move_time = limits
+                .times
+                .to_movetime(self.board.side_to_move, self.board.phase);
+        }
+        self.max_move_time =
+            max_move_time - self.move_overhead * (max_move_time > self.move_overhead) as u64;
+        self.max_depth = limits.depth.unwrap_or(i8::MAX);
+        self.max_nodes = limits.nodes.unwrap_or(u64::MAX);
+        self.infinite = limits.infinite;
+        self.root_moves = limits.search_moves.clone();
+        self.htable.age();
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder.store(limits.ponder, Ordering::Relaxed);
+    }
+
+    /// Searches the current position with the engine's threads
+    pub fn search(&mut self, limits: &SearchLimits) -> SearchResult {
+        self.set_limits(limits);
+        self.go_smp(self.threads)
+    }
+}
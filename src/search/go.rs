@@ -1,67 +1,251 @@
+use super::observer::IterationInfo;
+use super::result::{Score, SearchResult, StopReason};
 use super::*;
-use crate::io::outputs::uci_info;
+use crate::eval::wdl::wdl;
 use crate::position::{MoveList, MoveType};
+use crate::tables::search::Bound;
+use std::cmp::{max, min};
 use std::sync::atomic::Ordering;
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Initial half-width of the root aspiration window
+const ASPIRATION_DELTA: i32 = 30;
+const ASPIRATION_MIN_DEPTH: i8 = 5;
+/// Delay before reporting the current root move
+const CURRMOVE_DELAY: u128 = 3000;
+/// Interval between periodic node count reports
+const HEARTBEAT_INTERVAL: u128 = 1000;
 
 impl Engine {
-    /// iterative deepening search
-    /// CLI: command line output of info needed?
-    /// STATS: debug stats needed?
-    pub fn go<const CLI: bool>(&mut self) -> u16 {
+    /// iterative deepening search, reporting to the engine's observer
+    pub fn go(&mut self) -> SearchResult {
+        let result = self.iterative_deepening();
+        // an infinite or ponder search must not finish before stop (or ponderhit)
+        while (self.infinite || self.ponder.load(Ordering::Relaxed))
+            && !self.stop.load(Ordering::Relaxed)
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.observer.on_bestmove(&result);
+        result
+    }
+
+    fn iterative_deepening(&mut self) -> SearchResult {
+        self.stats.reset();
+        let mut result = SearchResult {
+            best_move: 0,
+            ponder_move: 0,
+            score: Score::Cp(0),
+            pv: Vec::new(),
+            depth: 0,
+            seldepth: 0,
+            nodes: 0,
+            time: 0,
+            stop_reason: StopReason::Depth,
+        };
+
         // if only one legal move, make it immediately
         let mut moves = MoveList::default();
         self.board.gen_moves::<{ MoveType::ALL }>(&mut moves);
-        if moves.len() == 1 {
-            return moves[0];
+        self.restrict_root_moves(&mut moves);
+        match moves.len() {
+            0 => {
+                result.stop_reason = StopReason::NoMoves;
+                return result;
+            }
+            1 => {
+                result.best_move = moves[0];
+                result.pv = vec![moves[0]];
+                result.stop_reason = StopReason::OnlyMove;
+                return result;
+            }
+            _ => (),
         }
+        let num_lines = self.multipv.clamp(1, moves.len());
 
         // loop of iterative deepening, up to preset max depth
-        self.stats.start_time = Instant::now();
-        let mut best_move = 0;
+        let mut prev_score = 0;
         let mut prev_m = 0;
         if !self.board.state_stack.is_empty() {
             prev_m = self.board.state_stack.last().unwrap().m;
         }
-        for d in 0..self.max_depth {
+        // helpers on odd threads start a ply deeper, to desynchronise the threads
+        for d in (self.thread_id & 1) as i8..self.max_depth.min(MAX_PLY - 1) {
             self.stats.seldepth = 0;
+            // multipv: each line excludes the best moves of the previous lines
+            self.excluded_moves.clear();
+            let mut lines: Vec<(i16, Vec<u16>)> = Vec::with_capacity(num_lines);
+            for _ in 0..num_lines {
+                // aspiration windows only make sense for a single line
+                let aspire = num_lines == 1 && d + 1 >= ASPIRATION_MIN_DEPTH;
+                let (score, pv) = self.search_root(d + 1, prev_m, prev_score, aspire, &result.pv);
+                if pv.is_empty() || self.stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                self.excluded_moves.push(pv[0]);
+                lines.push((score, pv));
+            }
+            self.excluded_moves.clear();
+
+            if self.stop.load(Ordering::Relaxed) || self.node_limit_reached() {
+                result.stop_reason = self.stop_reason();
+                break;
+            }
+            lines.sort_by_key(|line| std::cmp::Reverse(line.0));
+            let score = match lines.first() {
+                Some((score, pv)) => {
+                    result.best_move = pv[0];
+                    result.ponder_move = pv.get(1).copied().unwrap_or(0);
+                    result.pv = pv.clone();
+                    *score
+                }
+                None => 0,
+            };
+            prev_score = score;
+            result.score = Score::from(score);
+            result.depth = d + 1;
+            result.seldepth = self.stats.seldepth;
+            self.publish_nodes();
+            for (i, (score, pv)) in lines.into_iter().enumerate() {
+                let info = self.iteration_info(d + 1, i + 1, score, Bound::EXACT, pv);
+                self.observer.on_iteration(&info);
+            }
+            self.stats.last_info = Instant::now();
+
+            if is_mate_score(score) && !self.infinite {
+                result.stop_reason = StopReason::Mate;
+                break;
+            }
+        }
+        // stopped before the first iteration completed, any legal move beats none
+        if result.best_move == 0 {
+            result.best_move = moves[0];
+            result.pv = vec![moves[0]];
+        }
+        self.publish_nodes();
+        result.nodes = self.thread_nodes.total();
+        result.time = self.stats.start_time.elapsed().as_millis();
+        result
+    }
+
+    /// which limit ended the search early
+    fn stop_reason(&self) -> StopReason {
+        if self.node_limit_reached() {
+            StopReason::Nodes
+        } else if self.stats.timer.elapsed().as_millis() as u64 > self.max_move_time {
+            StopReason::Time
+        } else {
+            StopReason::Stopped
+        }
+    }
+
+    /// search of the root position, using an aspiration window around the previous
+    /// iteration's score if asked, which is widened on fail high/low
+    fn search_root(
+        &mut self,
+        depth: i8,
+        prev_m: u16,
+        prev_score: i16,
+        aspire: bool,
+        best_pv: &[u16],
+    ) -> (i16, Vec<u16>) {
+        let check = self.board.is_in_check();
+        let mut delta = ASPIRATION_DELTA;
+        let (mut alpha, mut beta) = (-MAX_SCORE as i32, MAX_SCORE as i32);
+        if aspire && !is_mate_score(prev_score) {
+            alpha = max(alpha, prev_score as i32 - delta);
+            beta = min(beta, prev_score as i32 + delta);
+        }
+        loop {
             let mut pv = Vec::new();
-            let check = self.board.is_in_check();
             let score = self.negamax::<true, true>(
-                -MAX_SCORE,
-                MAX_SCORE,
-                d + 1,
+                alpha as i16,
+                beta as i16,
+                depth,
                 0,
                 &mut pv,
                 prev_m,
                 check,
                 false,
             );
-
-            if self.stop.load(Ordering::Relaxed) || self.stats.node_count > self.max_nodes {
-                break;
+            if self.stop.load(Ordering::Relaxed) {
+                return (score, pv);
             }
-            if !pv.is_empty() {
-                best_move = pv[0];
-            }
-            let time = self.stats.start_time.elapsed().as_millis();
-            if CLI {
-                uci_info(
-                    d + 1,
-                    self.stats.seldepth,
-                    self.stats.node_count,
-                    time,
-                    pv,
-                    score,
-                    self.ttable.hashfull(),
-                );
+            let bound = if score as i32 <= alpha && alpha > -MAX_SCORE as i32 {
+                alpha = max(-MAX_SCORE as i32, alpha - delta);
+                Bound::UPPER
+            } else if score as i32 >= beta && beta < MAX_SCORE as i32 {
+                beta = min(MAX_SCORE as i32, beta + delta);
+                Bound::LOWER
+            } else {
+                return (score, pv);
+            };
+            delta *= 2;
+            // nothing better than last iteration's pv is known after failing low
+            if pv.is_empty() {
+                pv = best_pv.to_vec();
             }
+            let info = self.iteration_info(depth, 1, score, bound, pv);
+            self.observer.on_iteration(&info);
+        }
+    }
 
-            if is_mate_score(score) {
-                break;
-            }
+    fn iteration_info(
+        &self,
+        depth: i8,
+        multipv: usize,
+        score: i16,
+        bound: u8,
+        pv: Vec<u16>,
+    ) -> IterationInfo {
+        IterationInfo {
+            depth,
+            seldepth: self.stats.seldepth,
+            multipv,
+            score,
+            bound,
+            wdl: self.show_wdl.then(|| wdl(score, self.board.phase)),
+            pv,
+            nodes: self.thread_nodes.total(),
+            time: self.stats.start_time.elapsed().as_millis(),
+            hashfull: self.ttable.hashfull(),
         }
-        // resetting counts
-        best_move
+    }
+
+    /// reports the root move being searched, once the search has been running a while
+    pub fn report_currmove(&self, m: u16, m_idx: usize) {
+        if self.stats.start_time.elapsed().as_millis() > CURRMOVE_DELAY {
+            self.observer.on_currmove(m, m_idx + 1);
+        }
+    }
+
+    /// periodically reports node counts, so long iterations don't look frozen
+    pub fn heartbeat(&mut self) {
+        if self.stats.last_info.elapsed().as_millis() >= HEARTBEAT_INTERVAL {
+            self.observer.on_progress(
+                self.thread_nodes.total(),
+                self.stats.start_time.elapsed().as_millis(),
+                self.ttable.hashfull(),
+            );
+            self.stats.last_info = Instant::now();
+        }
+    }
+
+    /// lazy SMP search
+    /// runs threads - 1 helper engines alongside this one, all sharing the hash table,
+    /// and returns the result found by this (main) thread
+    pub fn go_smp(&mut self, threads: usize) -> SearchResult {
+        self.thread_nodes = Arc::new(ThreadNodes::new(threads));
+        let helpers: Vec<Engine> = (1..threads).map(|id| self.helper(id)).collect();
+        thread::scope(|s| {
+            for mut helper in helpers {
+                s.spawn(move || helper.go());
+            }
+            let result = self.go();
+            // helpers search until told to stop
+            self.stop.store(true, Ordering::Relaxed);
+            result
+        })
     }
 }
@@ -1,17 +1,24 @@
+pub mod builder;
 mod go;
 pub mod timings;
 #[rustfmt::skip]
 mod negamax;
+pub mod observer;
 mod pruning;
 mod qsearch;
+pub mod result;
 pub mod sorting;
 
 use crate::io::errors::UciError;
-use crate::position::{zobrist::ZobristVals, Position};
+use crate::position::{zobrist::ZobristVals, MoveList, Position};
+use crate::search::observer::{SearchObserver, SilentObserver};
 use crate::tables::history::HistoryTable;
 use crate::tables::killer::KillerMoveTable;
 use crate::tables::{countermove::CounterMoveTable, pawn::PawnHashTable, search::HashTable};
-use std::sync::{atomic::AtomicBool, Arc};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
 use std::time::Instant;
 
 pub const MAX_PLY: i8 = i8::MAX;
@@ -55,10 +62,29 @@ pub struct Engine {
     pub htable: Arc<HistoryTable>,
     // Search info
     pub stop: Arc<AtomicBool>,
+    pub ponder: Arc<AtomicBool>,
     pub max_move_time: u64,
     pub max_depth: i8,
     pub max_nodes: u64,
+    /// Analysis mode, search until stopped
+    pub infinite: bool,
+    /// Root moves to consider, all if empty
+    pub root_moves: Vec<u16>,
+    /// Number of principal variations to report
+    pub multipv: usize,
+    /// Root moves already reported this iteration
+    pub excluded_moves: Vec<u16>,
+    /// Report win/draw/loss probabilities
+    pub show_wdl: bool,
+    /// Receives info, currmove and bestmove reports
+    pub observer: Arc<dyn SearchObserver>,
     pub stats: Stats,
+    /// Threads used by search, and milliseconds kept back from timed searches
+    pub threads: usize,
+    pub move_overhead: u64,
+    // Lazy SMP info
+    pub thread_id: usize,
+    pub thread_nodes: Arc<ThreadNodes>,
 }
 
 impl Engine {
@@ -70,13 +96,14 @@ impl Engine {
         ptable: Arc<PawnHashTable>,
         zobrist_vals: Arc<ZobristVals>,
     ) -> Result<Self, UciError> {
-        let board = Position::from_fen(s, zobrist_vals)?;
+        let board =
+            Position::from_fen(s, zobrist_vals).map_err(|_| UciError::Fen(s.to_string()))?;
         Ok(Self::new(
             board,
             Arc::new(AtomicBool::new(false)),
-            0,
-            0,
-            0,
+            u64::MAX,
+            i8::MAX,
+            u64::MAX,
             ttable,
             ptable,
         ))
@@ -96,22 +123,82 @@ impl Engine {
         Self {
             board,
             stop,
+            ponder: Arc::new(AtomicBool::new(false)),
             max_move_time,
             max_depth,
             max_nodes,
+            infinite: false,
+            root_moves: Vec::new(),
+            multipv: 1,
+            excluded_moves: Vec::new(),
+            show_wdl: false,
+            observer: Arc::new(SilentObserver),
             ttable,
             ptable,
             ctable: Arc::new(CounterMoveTable::default()),
             ktable: Arc::new(KillerMoveTable::default()),
             htable: Arc::new(HistoryTable::default()),
             stats,
+            threads: 1,
+            move_overhead: 0,
+            thread_id: 0,
+            thread_nodes: Arc::new(ThreadNodes::new(1)),
         }
     }
 
+    /// Helper engine for lazy SMP, sharing the hash tables and stop signal
+    /// but with its own killer, counter move and history tables
+    pub fn helper(&self, thread_id: usize) -> Self {
+        let mut helper = Self::new(
+            self.board.clone(),
+            self.stop.clone(),
+            self.max_move_time,
+            self.max_depth,
+            u64::MAX,
+            self.ttable.clone(),
+            self.ptable.clone(),
+        );
+        helper.ponder = self.ponder.clone();
+        helper.infinite = self.infinite;
+        helper.root_moves = self.root_moves.clone();
+        helper.thread_id = thread_id;
+        helper.thread_nodes = self.thread_nodes.clone();
+        helper
+    }
+
+    /// Restricts the root move list to the requested root moves, if any,
+    /// minus the moves excluded by multipv
+    pub fn restrict_root_moves(&self, moves: &mut MoveList) {
+        if !self.root_moves.is_empty() {
+            moves.retain(|m| self.root_moves.contains(&m));
+        }
+        if !self.excluded_moves.is_empty() {
+            moves.retain(|m| !self.excluded_moves.contains(&m));
+        }
+    }
+
+    /// Publish this thread's node count so the main thread can report totals
+    #[inline(always)]
+    pub fn publish_nodes(&self) {
+        self.thread_nodes.set(self.thread_id, self.stats.node_count)
+    }
+
+    /// node limits apply to the total over all threads
+    #[inline(always)]
+    pub fn node_limit_reached(&self) -> bool {
+        self.publish_nodes();
+        self.thread_nodes.total() > self.max_nodes
+    }
+
     #[inline(always)]
-    pub fn search_limits_reached(&self) -> bool {
-        self.stats.node_count > self.max_nodes // node count reached
-        || self.stats.start_time.elapsed().as_millis() as u64 > self.max_move_time
+    pub fn search_limits_reached(&mut self) -> bool {
+        // no time limit while pondering, the clock starts on ponderhit
+        if self.ponder.load(Ordering::Relaxed) {
+            self.stats.timer = Instant::now();
+            return false;
+        }
+        self.node_limit_reached()
+            || self.stats.timer.elapsed().as_millis() as u64 > self.max_move_time
         // search time exceeded
     }
 }
@@ -122,6 +209,10 @@ pub struct Stats {
     /// Always tracked
     pub node_count: u64,
     pub start_time: Instant,
+    /// Time management clock
+    pub timer: Instant,
+    /// Time of the last info output
+    pub last_info: Instant,
     pub seldepth: i8,
 }
 impl Default for Stats {
@@ -130,6 +221,8 @@ impl Default for Stats {
             node_count: 0,
             seldepth: 0,
             start_time: Instant::now(),
+            timer: Instant::now(),
+            last_info: Instant::now(),
         }
     }
 }
@@ -139,3 +232,24 @@ impl Stats {
         *self = Self::default();
     }
 }
+
+/// Node counts of every thread in a lazy SMP search
+pub struct ThreadNodes {
+    counts: Vec<AtomicU64>,
+}
+impl ThreadNodes {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            counts: (0..threads).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn set(&self, thread_id: usize, nodes: u64) {
+        self.counts[thread_id].store(nodes, Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+    }
+}
@@ -33,9 +33,13 @@ impl Engine {
         if self.stop.load(Ordering::Relaxed) {
             return 0;
         }
-        if self.stats.node_count & 2047 == 0 && self.search_limits_reached() {
-            self.stop.store(true, Ordering::Relaxed);
-            return 0;
+        if self.stats.node_count & 2047 == 0 {
+            self.publish_nodes();
+            self.heartbeat();
+            if self.search_limits_reached() {
+                self.stop.store(true, Ordering::Relaxed);
+                return 0;
+            }
         }
         self.stats.seldepth = max(self.stats.seldepth, ply);
 
@@ -108,6 +112,9 @@ impl Engine {
         // generating moves
         let mut moves = MoveList::default();
         self.board.gen_moves::<{ MoveType::ALL }>(&mut moves);
+        if ROOT {
+            self.restrict_root_moves(&mut moves);
+        }
 
         // checking for (stale)mate
         if moves.is_empty() {
@@ -127,12 +134,16 @@ impl Engine {
         while let Some((m, m_idx, m_score)) = get_next_move(&mut moves, &mut move_scores) {
             let mut sub_pv = Vec::new();
 
+            if ROOT {
+                self.report_currmove(m, m_idx);
+            }
+
             self.board.make_move(m);
 
             // late move reductions
             let check = self.board.is_in_check();
             let do_lmr = can_do_lmr::<ROOT>(king_in_check, m_idx, m_score, check, depth);
-            let reduction = do_lmr as i8 * (1 + min(2 - PV as i8, ((m_idx - 2) / 4) as i8));
+            let reduction = do_lmr as i8 * (1 + min(2 - PV as i8, (m_idx.saturating_sub(2) / 4) as i8));
 
             // pvs framework
             // relies on good move ordering!
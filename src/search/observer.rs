@@ -0,0 +1,39 @@
+use super::result::SearchResult;
+
+/// A principal variation reported at the end of an iteration, or on an
+/// aspiration window failure
+#[derive(Clone, Debug)]
+pub struct IterationInfo {
+    pub depth: i8,
+    pub seldepth: i8,
+    /// Line number, starting from 1
+    pub multipv: usize,
+    pub score: i16,
+    /// Bound::EXACT, or Bound::LOWER/UPPER if the window failed high/low
+    pub bound: u8,
+    /// Win/draw/loss in permille, if requested
+    pub wdl: Option<[u16; 3]>,
+    pub pv: Vec<u16>,
+    /// Summed over all threads
+    pub nodes: u64,
+    /// Milliseconds since the search started
+    pub time: u128,
+    pub hashfull: u64,
+}
+
+/// Receives reports from the main search thread
+/// every event is ignored by default
+pub trait SearchObserver: Send + Sync {
+    /// a completed line, or a bound on one
+    fn on_iteration(&self, _info: &IterationInfo) {}
+    /// the root move being searched, numbered from 1
+    fn on_currmove(&self, _m: u16, _number: usize) {}
+    /// periodic node count, so long iterations don't look frozen
+    fn on_progress(&self, _nodes: u64, _time: u128, _hashfull: u64) {}
+    /// end of the search
+    fn on_bestmove(&self, _result: &SearchResult) {}
+}
+
+/// Reports nothing, used by lazy SMP helpers and library searches
+pub struct SilentObserver;
+impl SearchObserver for SilentObserver {}
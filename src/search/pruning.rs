@@ -22,21 +22,9 @@ pub fn tt_prune<const PV: bool>(
 ) -> Option<i16> {
     if res.depth >= depth && halfmove_clock <= 90 {
         match res.bound {
-            Bound::EXACT => {
-                if !PV {
-                    return Some(res.score);
-                }
-            }
-            Bound::LOWER => {
-                if res.score >= beta {
-                    return Some(beta);
-                }
-            }
-            Bound::UPPER => {
-                if res.score <= alpha {
-                    return Some(alpha);
-                }
-            }
+            Bound::EXACT if !PV => return Some(res.score),
+            Bound::LOWER if res.score >= beta => return Some(beta),
+            Bound::UPPER if res.score <= alpha => return Some(alpha),
             _ => (),
         }
     }
@@ -0,0 +1,63 @@
+use super::{is_mate_score, MAX_SCORE};
+
+/// Search score, from the side to move's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns
+    Cp(i16),
+    /// Moves until mate, negative if being mated
+    Mate(i16),
+}
+
+impl From<i16> for Score {
+    fn from(eval: i16) -> Self {
+        if !is_mate_score(eval) {
+            return Self::Cp(eval);
+        }
+        Self::Mate(
+            if eval < 0 {
+                eval.abs() - MAX_SCORE
+            } else {
+                MAX_SCORE - eval + 1
+            } / 2,
+        )
+    }
+}
+
+/// Why the search finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// Max depth was searched
+    Depth,
+    /// Node limit reached
+    Nodes,
+    /// Move time used up
+    Time,
+    /// Stop signal from outside the search
+    Stopped,
+    /// Forced mate found
+    Mate,
+    /// Only one legal root move, no search needed
+    OnlyMove,
+    /// No legal root moves
+    NoMoves,
+}
+
+/// Outcome of an iterative deepening search
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// 0 if there are no legal moves
+    pub best_move: u16,
+    /// Expected reply, 0 if unknown
+    pub ponder_move: u16,
+    pub score: Score,
+    pub pv: Vec<u16>,
+    /// Last fully searched depth
+    pub depth: i8,
+    pub seldepth: i8,
+    /// Summed over all threads
+    pub nodes: u64,
+    /// Milliseconds
+    pub time: u128,
+    pub stop_reason: StopReason,
+}
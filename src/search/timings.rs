@@ -1,5 +1,5 @@
 /// Move timing info
-#[derive(Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Times {
     /// White time on clock
     pub wtime: u64,
@@ -26,8 +26,8 @@ impl Times {
             _ => panic!("Invalid side!"),
         };
 
-        if self.moves_to_go.is_some() {
-            return available / self.moves_to_go.unwrap() as u64;
+        if let Some(moves_to_go) = self.moves_to_go {
+            return available / moves_to_go as u64;
         }
         available / (2 * (phase as u64 + 1))
     }
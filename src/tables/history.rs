@@ -58,9 +58,23 @@ impl HistoryTable {
         locale.data.store(new, Ordering::Relaxed)
     }
 
+    /// halves every score, so a long-lived table favours recent searches
+    /// and cannot overflow
+    pub fn age(&self) {
+        for side in &self.table {
+            for row in side {
+                for entry in row {
+                    entry.data.store(entry.get() / 2, Ordering::Relaxed);
+                }
+            }
+        }
+        let max = self.max.load(Ordering::Relaxed);
+        self.max.store(std::cmp::max(1, max / 2), Ordering::Relaxed);
+    }
+
     pub fn get(&self, side: usize, m: u16) -> i16 {
         let val = self.table[side][(m & 63) as usize][((m >> 6) & 63) as usize].get();
         let max = self.max.load(Ordering::Relaxed);
-        ((val * HISTORY_MAX as u32 + max - 1) / max) as i16
+        (val * HISTORY_MAX as u32).div_ceil(max) as i16
     }
 }
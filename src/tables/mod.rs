@@ -2,6 +2,7 @@ pub mod countermove;
 pub mod history;
 pub mod killer;
 pub mod pawn;
+pub mod perft;
 pub mod search;
 
 use std::{
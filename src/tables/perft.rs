@@ -0,0 +1,57 @@
+// Lockless table of perft subtree counts, shared by all perft threads
+// each entry stores the key xored with its data, so an entry torn by
+// concurrent writes fails verification instead of returning a wrong count
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Default)]
+pub struct PerftHashEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+impl Clone for PerftHashEntry {
+    fn clone(&self) -> Self {
+        Self {
+            key: AtomicU64::new(self.key.load(Ordering::Relaxed)),
+            data: AtomicU64::new(self.data.load(Ordering::Relaxed)),
+        }
+    }
+}
+const ENTRY_SIZE: usize = std::mem::size_of::<PerftHashEntry>();
+
+pub struct PerftHashTable {
+    table: Vec<PerftHashEntry>,
+    num_entries: usize,
+}
+
+impl PerftHashTable {
+    pub fn new(size: usize) -> Self {
+        let num_entries = (size / ENTRY_SIZE).max(1);
+        Self {
+            table: vec![Default::default(); num_entries],
+            num_entries,
+        }
+    }
+
+    /// always replaces, counts are limited to 56 bits
+    pub fn push(&self, zobrist: u64, depth: u8, count: u64) {
+        let data = (count << 8) | depth as u64;
+        let entry = &self.table[self.index(zobrist, depth)];
+        entry.key.store(zobrist ^ data, Ordering::Relaxed);
+        entry.data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, zobrist: u64, depth: u8) -> Option<u64> {
+        let entry = &self.table[self.index(zobrist, depth)];
+        let data = entry.data.load(Ordering::Relaxed);
+        if entry.key.load(Ordering::Relaxed) ^ data == zobrist && data as u8 == depth {
+            return Some(data >> 8);
+        }
+        None
+    }
+
+    /// the same position at different depths goes to different entries
+    fn index(&self, zobrist: u64, depth: u8) -> usize {
+        (zobrist.rotate_left(depth as u32 * 8) as usize) % self.num_entries
+    }
+}
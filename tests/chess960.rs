@@ -0,0 +1,172 @@
+// Chess960 move generation, checked against leaf counts from the standard
+// Fischer Random perft positions, with Shredder-FEN and X-FEN castling fields
+// depths with more than NODE_LIMIT leaves are skipped to keep test builds quick
+
+use kimbo::io::inputs::uci_to_u16;
+use kimbo::io::outputs::u16_to_uci;
+use kimbo::position::perft::perft;
+use kimbo::position::{zobrist::ZobristVals, Position};
+use std::sync::Arc;
+
+const NODE_LIMIT: u64 = 2_000_000;
+
+const POSITIONS: [(&str, [u64; 5]); 13] = [
+    // rooks on f1 and h1, as Shredder-FEN then X-FEN
+    (
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        [21, 528, 12189, 326672, 8146062],
+    ),
+    (
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+        [21, 528, 12189, 326672, 8146062],
+    ),
+    (
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        [21, 807, 18002, 667366, 16253601],
+    ),
+    // only white can castle
+    (
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        [20, 479, 10471, 273318, 6417013],
+    ),
+    // only black can castle
+    (
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+        [22, 593, 13440, 382958, 9183776],
+    ),
+    (
+        "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+        [28, 1120, 31058, 1171749, 34030312],
+    ),
+    (
+        "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+        [29, 899, 26578, 824055, 24851983],
+    ),
+    (
+        "q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9",
+        [30, 860, 24566, 732757, 21093346],
+    ),
+    (
+        "qbn1brkr/ppp1p1p1/2n4p/3p1p2/P7/6PP/QPPPPP2/1BNNBRKR w HFhf - 0 9",
+        [25, 635, 17054, 465806, 13203304],
+    ),
+    // king off its back rank
+    (
+        "qn1rbbkr/ppp2p1p/1n1pp1p1/8/3P4/P6P/1PP1PPPK/QNNRBB1R w hd - 2 9",
+        [28, 811, 23175, 679699, 19836606],
+    ),
+    // rook on f1 stays put when castling short
+    (
+        "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        [29, 502, 14569, 287739, 8652810],
+    ),
+    // king on g1 stays put when castling short, as Shredder-FEN then X-FEN
+    (
+        "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9",
+        [27, 916, 25798, 890435, 26302461],
+    ),
+    (
+        "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w KQkq - 0 9",
+        [27, 916, 25798, 890435, 26302461],
+    ),
+];
+
+fn position(fen: &str) -> Position {
+    Position::from_fen(fen, Arc::new(ZobristVals::default())).unwrap()
+}
+
+#[test]
+fn standard_positions() {
+    for (fen, counts) in POSITIONS {
+        let mut pos = position(fen);
+        for (depth, &expected) in counts.iter().enumerate() {
+            if expected <= NODE_LIMIT {
+                let found = perft::<false, false>(&mut pos, depth as u8 + 1);
+                assert_eq!(found, expected, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn xfen_matches_shredder_fen() {
+    for (xfen, shredder) in [
+        (POSITIONS[1].0, POSITIONS[0].0),
+        (POSITIONS[12].0, POSITIONS[11].0),
+    ] {
+        let (xfen, shredder) = (position(xfen), position(shredder));
+        assert_eq!(xfen.castle_rights, shredder.castle_rights);
+        assert_eq!(xfen.castle_rooks, shredder.castle_rooks);
+    }
+}
+
+#[test]
+fn king_next_to_rook() {
+    // the king on b1 castles long onto c1, with the rook jumping from a1 to d1,
+    // so 4 king moves, 7 + 12 rook moves and both castles
+    let white = "4k3/8/8/8/8/8/8/RK5R w HA - 0 1";
+    let black = "rk5r/8/8/8/8/8/8/4K3 b ha - 0 1";
+    assert_eq!(perft::<false, false>(&mut position(white), 1), 25);
+    for depth in 1..=4 {
+        assert_eq!(
+            perft::<false, false>(&mut position(white), depth),
+            perft::<false, false>(&mut position(black), depth),
+            "depth {}",
+            depth
+        );
+    }
+}
+
+#[test]
+fn castling_rights_need_a_rook() {
+    for (fen, valid) in [
+        // no rooks at all
+        (
+            "4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ),
+        // nothing beyond the king on h1
+        (
+            "4k3/8/8/8/8/8/8/R6K w K - 0 1",
+            "4k3/8/8/8/8/8/8/R6K w - - 0 1",
+        ),
+        // the rook on h1 is black's, and there is no rook on the g-file
+        (
+            "4k3/8/8/8/8/8/8/R3K2r w KQGg - 0 1",
+            "4k3/8/8/8/8/8/8/R3K2r w Q - 0 1",
+        ),
+        // black's king isn't on its back rank
+        (
+            "r6r/4k3/8/8/8/8/8/4K3 w kq - 0 1",
+            "r6r/4k3/8/8/8/8/8/4K3 w - - 0 1",
+        ),
+    ] {
+        let (pos, expected) = (position(fen), position(valid));
+        assert_eq!(pos.castle_rights, expected.castle_rights, "{}", fen);
+        for depth in 1..=3 {
+            assert_eq!(
+                perft::<false, false>(&mut position(fen), depth),
+                perft::<false, false>(&mut position(valid), depth),
+                "{} depth {}",
+                fen,
+                depth
+            );
+        }
+    }
+    assert_eq!(
+        perft::<false, false>(&mut position("4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1"), 1),
+        5
+    );
+}
+
+#[test]
+fn castle_notation_depends_on_the_mode() {
+    // the mode is passed in, so both notations can be used side by side
+    let pos = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let castle = uci_to_u16(&pos, "e1h1", true).unwrap();
+    assert_eq!(uci_to_u16(&pos, "e1g1", false).unwrap(), castle);
+    assert_eq!(uci_to_u16(&pos, "e1h1", false).unwrap(), castle);
+    assert!(uci_to_u16(&pos, "e1g1", true).is_err());
+    assert_eq!(u16_to_uci(&castle, true), "e1h1 ");
+    assert_eq!(u16_to_uci(&castle, false), "e1g1 ");
+}
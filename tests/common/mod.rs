@@ -0,0 +1,34 @@
+// helpers shared by the protocol tests
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// starts the kimbo binary in `protocol` mode and sends the commands, then collects
+/// output up to and including the first line starting with `until`, or everything
+/// if the engine exits first
+pub fn session(protocol: &str, commands: &[&str], until: &str) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kimbo"))
+        .arg(protocol)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for command in commands {
+        writeln!(stdin, "{}", command).unwrap();
+    }
+    let mut lines = Vec::new();
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let line = line.unwrap();
+        let done = line.starts_with(until);
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+    // the engine may already have exited
+    let _ = writeln!(stdin, "quit");
+    child.wait().unwrap();
+    lines
+}
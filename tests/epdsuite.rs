@@ -0,0 +1,76 @@
+// Search regression tests on a small tactical suite, searched to a fixed
+// depth so the results don't depend on the speed of the machine
+
+use kimbo::io::epdsuite::{load_epd_suite, parse_epd_suite, run_epd_suite};
+use kimbo::io::errors::EpdSuiteError;
+use kimbo::search::builder::SearchLimits;
+use kimbo::search::result::Score;
+
+#[test]
+fn parses_operations() {
+    let entries = parse_epd_suite(
+        "# comment\n\
+         r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - bm Qxh7+ Qh6xh7; id \"WAC.004; the queen\";\n\
+         7K/8/k1P5/7p/8/8/8/8 w - - 3 40 am Kg8 c7; c0 \"draw\";\n\
+         7K/8/k1P5/7p/8/8/8/8 w - - dm 2; hmvc 7; fmvn 12;\n",
+    )
+    .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].id, "WAC.004; the queen");
+    assert_eq!(entries[0].best_moves.len(), 2);
+    assert_eq!(entries[0].best_moves[0], entries[0].best_moves[1]);
+    assert_eq!(entries[1].id, "line 3");
+    assert_eq!(entries[1].fen, "7K/8/k1P5/7p/8/8/8/8 w - - 3 40");
+    assert_eq!(entries[1].avoid_moves.len(), 2);
+    assert_eq!(entries[2].fen, "7K/8/k1P5/7p/8/8/8/8 w - - 7 12");
+    assert_eq!(entries[2].mate, Some(2));
+
+    let reti = &entries[1];
+    assert!(!reti.is_solution(reti.avoid_moves[0], Score::Cp(0)));
+    assert!(reti.is_solution(entries[0].best_moves[0], Score::Cp(0)));
+    let mate = &entries[2];
+    assert!(mate.is_solution(0, Score::Mate(1)));
+    assert!(!mate.is_solution(0, Score::Mate(3)));
+    assert!(!mate.is_solution(0, Score::Mate(-2)));
+    assert!(!mate.is_solution(0, Score::Cp(900)));
+}
+
+#[test]
+fn reports_bad_lines() {
+    for (text, line) in [
+        (
+            "7K/8/k1P5/7p/8/8/8/8 w - - bm Kg7;\n\n7K/8/k1P5/7p/8/8/8/8 w - - bm Kf7;",
+            3,
+        ),
+        ("7K/8/k1P5/7p/8/8/8/8 w - - id \"no answer\";", 1),
+        ("7K/8/k1P5/7p/8/8/8/8 w - - dm x;", 1),
+        ("7K/8/k1P5/7p/8/8/8/9 w - - bm Kg7;", 1),
+        ("7K/8/k1P5/7p w", 1),
+    ] {
+        match parse_epd_suite(text) {
+            Err(EpdSuiteError::Line(found, _)) => assert_eq!(found, line, "{text}"),
+            _ => panic!("expected an error on line {line}: {text}"),
+        }
+    }
+}
+
+#[test]
+fn solves_tactics_suite() {
+    let entries =
+        load_epd_suite(&format!("{}/tests/tactics.epd", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let limits = SearchLimits {
+        depth: Some(12),
+        ..Default::default()
+    };
+    let report = run_epd_suite(&entries, &limits, 16, 1);
+    assert_eq!(report.results.len(), entries.len());
+    let failed: Vec<&str> = report.failed().map(|result| result.id.as_str()).collect();
+    assert!(failed.is_empty(), "failed {:?}", failed);
+    let mate = report
+        .results
+        .iter()
+        .find(|result| result.id == "mate.002")
+        .unwrap();
+    assert_eq!((mate.found.as_str(), mate.score), ("Rxh7+", Score::Mate(2)));
+    assert!(mate.depth <= 12);
+}
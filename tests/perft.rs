@@ -0,0 +1,133 @@
+// Move generation regression tests, checked against known perft leaf counts
+// counts above NODE_LIMIT are skipped to keep unoptimised test builds quick,
+// run `kimbo perftsuite tests/perftsuite.epd` for the full suite
+
+use kimbo::io::errors::PerftSuiteError;
+use kimbo::io::perftsuite::{load_perft_suite, parse_perft_suite, run_perft_suite};
+use kimbo::position::perft::{parallel_perft, perft, perft_stats};
+use kimbo::position::{zobrist::ZobristVals, Position};
+use std::sync::Arc;
+
+const NODE_LIMIT: u64 = 1_000_000;
+
+fn suite_path() -> String {
+    format!("{}/tests/perftsuite.epd", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn standard_suite() {
+    let entries = load_perft_suite(&suite_path()).unwrap();
+    let report = run_perft_suite(&entries, NODE_LIMIT);
+    assert_eq!(report.positions, entries.len());
+    assert!(report.checked > 0);
+    assert!(
+        report.passed(),
+        "{} wrong leaf counts",
+        report.mismatches.len()
+    );
+}
+
+#[test]
+fn mismatch_is_reported() {
+    let entries = parse_perft_suite(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 401",
+    )
+    .unwrap();
+    let report = run_perft_suite(&entries, NODE_LIMIT);
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.mismatches.len(), 1);
+    let mismatch = &report.mismatches[0];
+    assert_eq!(
+        (mismatch.depth, mismatch.expected, mismatch.found),
+        (2, 401, 400)
+    );
+}
+
+#[test]
+fn node_limit_skips_depths() {
+    let entries = parse_perft_suite(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D4 197281",
+    )
+    .unwrap();
+    let report = run_perft_suite(&entries, 1000);
+    assert_eq!((report.checked, report.skipped), (1, 1));
+}
+
+#[test]
+fn malformed_lines() {
+    let suite = "# comment\n\n8/8/8/8/8/8/8/8 w - - 0 1 ;D1 0";
+    assert!(matches!(
+        parse_perft_suite(suite),
+        Err(PerftSuiteError::Line(3, _))
+    ));
+    let suite = "4k3/8/8/8/8/8/8/4K3 w - - 0 1 ;D1 five";
+    assert!(matches!(
+        parse_perft_suite(suite),
+        Err(PerftSuiteError::Line(1, _))
+    ));
+    let suite = "4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+    assert!(matches!(
+        parse_perft_suite(suite),
+        Err(PerftSuiteError::Line(1, _))
+    ));
+}
+
+#[test]
+fn parallel_and_hashed_counts_match() {
+    let zvals = Arc::new(ZobristVals::default());
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ] {
+        let mut pos = Position::from_fen(fen, zvals.clone()).unwrap();
+        let expected = perft::<false, false>(&mut pos, 3);
+        assert_eq!(parallel_perft(&pos, 3, 1, 0, false), expected);
+        assert_eq!(parallel_perft(&pos, 3, 3, 0, false), expected);
+        // a tiny table forces collisions and overwrites
+        assert_eq!(parallel_perft(&pos, 3, 3, 1, false), expected);
+        assert_eq!(
+            parallel_perft(&pos, 4, 2, 1, false),
+            perft::<false, false>(&mut pos, 4)
+        );
+    }
+}
+
+#[test]
+fn stats_match_published_tables() {
+    // nodes, captures, e.p., castles, promotions, checks, discovered, double, checkmates
+    let zvals = Arc::new(ZobristVals::default());
+    let table = [
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            3,
+            [97862, 17102, 45, 3162, 0, 993, 0, 0, 1],
+        ),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            5,
+            [674624, 52051, 1165, 0, 0, 52950, 1292, 3, 0],
+        ),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            3,
+            [9467, 1021, 4, 0, 120, 38, 2, 0, 22],
+        ),
+    ];
+    for (fen, depth, counts) in table {
+        let mut pos = Position::from_fen(fen, zvals.clone()).unwrap();
+        let stats = perft_stats(&mut pos, depth);
+        let found = [
+            stats.nodes,
+            stats.captures,
+            stats.en_passant,
+            stats.castles,
+            stats.promotions,
+            stats.checks,
+            stats.discovered_checks,
+            stats.double_checks,
+            stats.checkmates,
+        ];
+        assert_eq!(found, counts, "{fen}");
+    }
+}
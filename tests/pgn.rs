@@ -0,0 +1,234 @@
+use kimbo::io::errors::{PgnError, SanError};
+use kimbo::io::pgn::{read_pgn, GameResult, PgnGame, PgnReader};
+use kimbo::position::{zobrist::ZobristVals, Position};
+use kimbo::search::result::Score;
+use std::sync::Arc;
+
+const GAMES: &str = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[White "Kimbo"]
+[Black "Kimbo"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {the main line; with a comment
+spanning lines} Nc6 (2... d6 3. d4 (3. Bc4 Be7) exd4) 3. Bb5 $1 a6 4.Ba4 Nf6
+5. O-O!? Be7 ; rest of line comment (
+6. Re1 b5 7. Bb3 d6 8. c3 O-O 1-0
+
+[Event "Setup"]
+[SetUp "1"]
+[FEN "8/4P3/8/8/8/8/k7/4K3 w - - 0 1"]
+[Result "1/2-1/2"]
+
+1. e8=Q Kb2 2. Qe5+ 1/2-1/2
+
+[Event "Black to move"]
+[FEN "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"]
+
+1... c5 2. Nf3 *
+"#;
+
+#[test]
+fn reads_tags_moves_and_results() {
+    let games = read_pgn(GAMES).unwrap();
+    assert_eq!(games.len(), 3);
+
+    let game = &games[0];
+    assert_eq!(game.tag("Event"), Some(r#"Casual "blitz""#));
+    assert_eq!(game.tag("White"), Some("Kimbo"));
+    assert_eq!(game.tag("Round"), None);
+    assert_eq!(game.result, GameResult::WhiteWins);
+    assert_eq!(game.moves.len(), 16);
+    assert_eq!(
+        game.final_position().to_fen(),
+        "r1bq1rk1/2p1bppp/p1np1n2/1p2p3/4P3/1BP2N2/PP1P1PPP/RNBQR1K1 w - - 1 9"
+    );
+
+    let game = &games[1];
+    assert_eq!(game.start.to_fen(), "8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+    assert_eq!(game.moves.len(), 3);
+    assert_eq!(game.result, GameResult::Draw);
+
+    let game = &games[2];
+    assert_eq!(game.moves.len(), 2);
+    assert_eq!(game.result, GameResult::Unknown);
+}
+
+#[test]
+fn games_without_results_or_tags() {
+    let games = read_pgn("1. d4 d5 2. c4\n\n[Event \"next\"]\n1. e4 1-0\n\n1. Nf3 *\n").unwrap();
+    assert_eq!(games.len(), 3);
+    assert_eq!(games[0].moves.len(), 3);
+    assert_eq!(games[0].result, GameResult::Unknown);
+    assert_eq!(games[1].tag("Event"), Some("next"));
+    assert_eq!(games[1].moves.len(), 1);
+    assert_eq!(games[1].result, GameResult::WhiteWins);
+    assert_eq!(games[2].moves.len(), 1);
+}
+
+#[test]
+fn errors_name_the_move_and_reading_recovers() {
+    let pgn = "[Event \"bad\"]\n\n1. e4 e5 2. Ke3 Nc6 *\n\n[Event \"ambiguous\"]\n\n\
+               1. d4 d5 2. Nf3 Nf6 3. Nd2 *\n\n[Event \"bad tag\n[Event \"ok\"]\n\n1. c4 *\n\n\
+               [Event \"ok\"]\n\n1. d4 *\n";
+    let games: Vec<_> = PgnReader::new(pgn.as_bytes()).collect();
+    assert_eq!(games.len(), 4);
+    match &games[0] {
+        Err(PgnError::Move {
+            line,
+            move_number,
+            error,
+        }) => {
+            assert_eq!(*line, 3);
+            assert_eq!(move_number, "2.");
+            assert_eq!(*error, SanError::Illegal(String::from("Ke3")));
+        }
+        _ => panic!("expected an illegal move"),
+    }
+    match &games[1] {
+        Err(PgnError::Move { error, .. }) => {
+            assert_eq!(*error, SanError::Ambiguous(String::from("Nd2")))
+        }
+        _ => panic!("expected an ambiguous move"),
+    }
+    // the rest of a game with a malformed tag is skipped
+    assert!(matches!(games[2], Err(PgnError::Tag(9, _))));
+    let game = games[3].as_ref().unwrap();
+    assert_eq!(game.moves.len(), 1);
+}
+
+#[test]
+fn invalid_setup_and_variations() {
+    assert!(matches!(
+        read_pgn("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n\n1. e4 *"),
+        Err(PgnError::Fen(3, _))
+    ));
+    assert!(matches!(
+        read_pgn("1. e4 ) e5 *"),
+        Err(PgnError::Variation(1, _))
+    ));
+    assert!(matches!(
+        read_pgn("1. e4 (1. d4)) e5 *"),
+        Err(PgnError::Variation(1, _))
+    ));
+    assert!(matches!(
+        read_pgn("(1. d4) 1. e4 *"),
+        Err(PgnError::Variation(1, _))
+    ));
+}
+
+#[test]
+fn variations_are_kept() {
+    let games = read_pgn(GAMES).unwrap();
+    let game = &games[0];
+    assert_eq!(game.variations.len(), 1);
+    // 2... d6 replaces 2... Nc6
+    let variation = &game.variations[0];
+    assert_eq!(variation.ply, 3);
+    assert_eq!(variation.moves.len(), 3);
+    let mut pos = game.start.clone();
+    for &m in &game.moves[..3] {
+        pos.make_move(m);
+    }
+    assert_eq!(variation.moves[0], pos.parse_san("d6").unwrap());
+    // 3. Bc4 Be7 replaces 3. d4
+    assert_eq!(variation.variations.len(), 1);
+    assert_eq!(variation.variations[0].ply, 1);
+    assert_eq!(variation.variations[0].moves.len(), 2);
+    assert!(variation.variations[0].variations.is_empty());
+    assert!(games[1].variations.is_empty());
+}
+
+#[test]
+fn illegal_moves_in_variations() {
+    // the variation is played from the position before e4
+    match read_pgn("1. e4 (1. Ke2) e5 *") {
+        Err(PgnError::Move {
+            line,
+            move_number,
+            error,
+        }) => {
+            assert_eq!(line, 1);
+            assert_eq!(move_number, "1.");
+            assert_eq!(error, SanError::Illegal(String::from("Ke2")));
+        }
+        _ => panic!("expected an illegal move"),
+    }
+    assert!(read_pgn("1. e4 (1. d4) e5 *").is_ok());
+    assert!(read_pgn("1. e4 (1. e5) *").is_err());
+}
+
+#[test]
+fn unclosed_variations_end_at_the_next_game() {
+    for pgn in [
+        "[Event \"open\"]\n\n1. e4 (1. d4 d5\n\n[Event \"next\"]\n\n1. c4 *\n",
+        "[Event \"open\"]\n\n1. e4 (1. d4 d5\n[Event \"next\"]\n\n1. c4 *\n",
+        "[Event \"open\"]\n\n1. e4 (1. d4 d5 *\n\n[Event \"next\"]\n\n1. c4 *\n",
+    ] {
+        let games: Vec<_> = PgnReader::new(pgn.as_bytes()).collect();
+        assert_eq!(games.len(), 2, "{pgn}");
+        assert!(matches!(games[0], Err(PgnError::Variation(..))), "{pgn}");
+        let game = games[1].as_ref().unwrap();
+        assert_eq!(game.tag("Event"), Some("next"));
+        assert_eq!(game.moves.len(), 1);
+    }
+    assert!(matches!(
+        read_pgn("1. e4 (1. d4"),
+        Err(PgnError::Variation(1, _))
+    ));
+}
+
+#[test]
+fn written_games_read_back() {
+    for game in read_pgn(GAMES).unwrap() {
+        let written = game.to_pgn();
+        let read = read_pgn(&written).unwrap();
+        assert_eq!(read.len(), 1, "{written}");
+        assert_eq!(read[0].moves, game.moves);
+        assert_eq!(read[0].variations, game.variations);
+        assert_eq!(read[0].result, game.result);
+        assert_eq!(read[0].start.to_fen(), game.start.to_fen());
+        assert_eq!(read[0].tag("Event"), game.tag("Event"));
+        assert!(written.lines().all(|line| line.len() <= 79));
+    }
+}
+
+#[test]
+fn writes_export_format() {
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    let mut game = PgnGame::new(Position::from_fen(fen, Arc::new(ZobristVals::default())).unwrap());
+    game.set_tag("White", "Kimbo");
+    game.set_tag("Black", "Kimbo");
+    game.set_tag("Black", "Kimbo \"dev\"");
+    game.set_tag("TimeControl", "10+0.1");
+    let mut pos = game.start.clone();
+    for (san, score) in [
+        ("c5", Some(Score::Cp(-35))),
+        ("Nf3", None),
+        ("Nc6", Some(Score::Cp(-20))),
+        ("d4", Some(Score::Mate(-12))),
+    ] {
+        let m = pos.parse_san(san).unwrap();
+        pos.make_move(m);
+        game.moves.push(m);
+        game.scores.push(score);
+    }
+    game.result = GameResult::Draw;
+    assert_eq!(
+        game.to_pgn(),
+        r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "Kimbo"]
+[Black "Kimbo \"dev\""]
+[Result "1/2-1/2"]
+[SetUp "1"]
+[FEN "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"]
+[TimeControl "10+0.1"]
+
+1... c5 {[%eval 0.35]} 2. Nf3 Nc6 {[%eval 0.20]} 3. d4 {[%eval #-12]} 1/2-1/2
+
+"#
+    );
+}
@@ -0,0 +1,106 @@
+use kimbo::io::errors::SanError;
+use kimbo::position::{zobrist::ZobristVals, MoveList, MoveType, Position};
+use std::sync::Arc;
+
+fn position(fen: &str) -> Position {
+    Position::from_fen(fen, Arc::new(ZobristVals::default())).unwrap()
+}
+
+fn san_of(pos: &Position, san: &str) -> String {
+    pos.move_to_san(pos.parse_san(san).unwrap())
+}
+
+#[test]
+fn round_trip_every_legal_move() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ] {
+        let mut pos = position(fen);
+        let mut moves = MoveList::default();
+        pos.gen_moves::<{ MoveType::ALL }>(&mut moves);
+        for i in 0..moves.len() {
+            let m = moves[i];
+            assert_eq!(pos.parse_san(&pos.move_to_san(m)), Ok(m), "{fen}");
+            // and one ply deeper
+            pos.make_move(m);
+            let mut replies = MoveList::default();
+            pos.gen_moves::<{ MoveType::ALL }>(&mut replies);
+            for j in 0..replies.len() {
+                let reply = replies[j];
+                assert_eq!(pos.parse_san(&pos.move_to_san(reply)), Ok(reply));
+            }
+            pos.unmake_move();
+        }
+    }
+}
+
+#[test]
+fn disambiguation() {
+    let pos = position("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+    assert_eq!(san_of(&pos, "Nbd2"), "Nbd2");
+    assert_eq!(san_of(&pos, "Nfd2"), "Nfd2");
+    assert_eq!(san_of(&pos, "Nc3"), "Nc3");
+    let pos = position("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+    assert_eq!(san_of(&pos, "R1a3"), "R1a3");
+    assert_eq!(san_of(&pos, "R5a3"), "R5a3");
+    let pos = position("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1");
+    assert_eq!(san_of(&pos, "Qa1b2"), "Qa1b2");
+    assert_eq!(san_of(&pos, "Qcb2"), "Qcb2");
+}
+
+#[test]
+fn castles_checks_and_promotions() {
+    let pos = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    assert_eq!(san_of(&pos, "O-O"), "O-O");
+    assert_eq!(san_of(&pos, "0-0-0"), "O-O-O");
+    assert_eq!(san_of(&pos, "Qxf6"), "Qxf6");
+    let pos = position("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2");
+    assert_eq!(san_of(&pos, "Qh4"), "Qh4#");
+    let pos = position("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+    for promo in ["e8=Q", "e8Q", "e8(Q)", "e8q", "e7e8q"] {
+        assert_eq!(san_of(&pos, promo), "e8=Q");
+    }
+    assert_eq!(san_of(&pos, "e8=R"), "e8=R");
+    assert_eq!(
+        pos.parse_san("e8"),
+        Err(SanError::Illegal(String::from("e8")))
+    );
+}
+
+#[test]
+fn lenient_pawn_moves() {
+    let pos = position("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2");
+    for capture in ["exd5", "ed5", "e4xd5", "exd5!?", "exd5+"] {
+        assert_eq!(san_of(&pos, capture), "exd5");
+    }
+    assert_eq!(san_of(&pos, "e5"), "e5");
+    assert_eq!(san_of(&pos, "d2-d4"), "d4");
+    assert_eq!(san_of(&pos, "Ng1f3"), "Nf3");
+}
+
+#[test]
+fn errors() {
+    let pos = position("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+    assert_eq!(
+        pos.parse_san("Nd2"),
+        Err(SanError::Ambiguous(String::from("Nd2")))
+    );
+    assert_eq!(
+        pos.parse_san("Qh5"),
+        Err(SanError::Illegal(String::from("Qh5")))
+    );
+    assert_eq!(
+        pos.parse_san("O-O"),
+        Err(SanError::Illegal(String::from("O-O")))
+    );
+    for invalid in ["", "Zz9", "Ni9", "N", "Nb1c3d2"] {
+        assert_eq!(
+            pos.parse_san(invalid),
+            Err(SanError::Invalid(invalid.to_string()))
+        );
+    }
+}
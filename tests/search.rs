@@ -0,0 +1,26 @@
+// Search limits through the library engine api
+
+use kimbo::search::builder::SearchLimits;
+use kimbo::search::result::StopReason;
+use kimbo::search::Engine;
+
+#[test]
+fn node_limit_counts_every_thread() {
+    let limits = SearchLimits {
+        nodes: Some(100_000),
+        ..Default::default()
+    };
+    for threads in [1, 2, 4] {
+        let mut engine = Engine::builder().threads(threads).build();
+        let result = engine.search(&limits);
+        assert_eq!(result.stop_reason, StopReason::Nodes, "{} threads", threads);
+        // limits are only checked every few thousand nodes on each thread,
+        // but must not be applied per thread
+        assert!(
+            result.nodes < 125_000,
+            "{} nodes with {} threads",
+            result.nodes,
+            threads
+        );
+    }
+}
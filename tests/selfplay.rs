@@ -0,0 +1,94 @@
+use kimbo::io::selfplay::{run_match, Adjudication, EngineConfig, MatchConfig, TimeControl};
+use kimbo::io::sprt::{elo_estimate, elo_to_score, score_to_elo, Pentanomial, Sprt, SprtOutcome};
+
+const SPRT: Sprt = Sprt {
+    elo0: 0.0,
+    elo1: 10.0,
+    alpha: 0.05,
+    beta: 0.05,
+};
+
+#[test]
+fn elo_conversions() {
+    assert_eq!(elo_to_score(0.0), 0.5);
+    for elo in [-400.0, -35.5, 0.0, 10.0, 200.0] {
+        assert!((score_to_elo(elo_to_score(elo)) - elo).abs() < 1e-9);
+    }
+    let (elo, error) = elo_estimate(&Pentanomial([5, 10, 20, 10, 5]));
+    assert!(elo.abs() < 1e-9);
+    assert!(error > 0.0);
+    let (elo, _) = elo_estimate(&Pentanomial([5, 10, 20, 20, 10]));
+    assert!(elo > 0.0);
+}
+
+#[test]
+fn sprt_bounds_and_outcomes() {
+    assert!((SPRT.upper_bound() - 2.944).abs() < 1e-3);
+    assert!((SPRT.lower_bound() + 2.944).abs() < 1e-3);
+    // no information yet
+    assert_eq!(SPRT.llr(&Pentanomial::default()), 0.0);
+    assert_eq!(SPRT.llr(&Pentanomial([0, 0, 30, 0, 0])), 0.0);
+
+    let even = Pentanomial([500, 1000, 2000, 1000, 500]);
+    assert!(SPRT.llr(&even) < 0.0);
+    assert_eq!(SPRT.outcome(&even), SprtOutcome::AcceptH0);
+    let strong = Pentanomial([50, 100, 200, 300, 200]);
+    assert_eq!(SPRT.outcome(&strong), SprtOutcome::AcceptH1);
+    assert_eq!(
+        SPRT.outcome(&Pentanomial([1, 2, 3, 2, 1])),
+        SprtOutcome::Continue
+    );
+
+    let mut results = Pentanomial::default();
+    results.add_pair(4);
+    results.add_pair(2);
+    assert_eq!(results, Pentanomial([0, 0, 1, 0, 1]));
+    assert_eq!(results.pairs(), 2);
+}
+
+#[test]
+fn fixed_depth_match() {
+    let config = MatchConfig {
+        engines: [
+            EngineConfig::new("a")
+                .parse_options("depth=3,hash=1")
+                .unwrap(),
+            EngineConfig::new("b")
+                .parse_options("name=c,nodes=500")
+                .unwrap(),
+        ],
+        time_control: TimeControl::parse("1+0.01").unwrap(),
+        openings: vec![String::from(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        )],
+        max_pairs: 1,
+        sprt: Some(SPRT),
+        adjudication: Adjudication::default(),
+        pgn_file: None,
+    };
+    assert_eq!(config.engines[1].name, "c");
+    let report = run_match(&config).unwrap();
+    assert_eq!(report.wins + report.draws + report.losses, 2);
+    assert_eq!(report.pentanomial.pairs(), 1);
+    assert_eq!(report.outcome, Some(SprtOutcome::Continue));
+}
+
+#[test]
+fn option_errors() {
+    assert!(EngineConfig::new("a").parse_options("depth=x").is_err());
+    assert!(EngineConfig::new("a").parse_options("speed=3").is_err());
+    assert!(EngineConfig::new("a").parse_options("hash=0").is_err());
+    assert!(EngineConfig::new("a")
+        .parse_options("hash=1000000")
+        .is_err());
+    assert_eq!(
+        EngineConfig::new("a")
+            .parse_options("hash=512")
+            .unwrap()
+            .hash_size,
+        512
+    );
+    assert!(TimeControl::parse("10+").is_none());
+    let tc = TimeControl::parse("8+0.08").unwrap();
+    assert_eq!((tc.base, tc.increment), (8000, 80));
+}
@@ -0,0 +1,81 @@
+// uci protocol tests, talking to the kimbo binary over stdin and stdout
+
+mod common;
+
+fn session(commands: &[&str], until: &str) -> Vec<String> {
+    common::session("uci", commands, until)
+}
+
+#[test]
+fn go_without_a_value_is_rejected() {
+    for go in [
+        "go depth",
+        "go depth nodes 1000",
+        "go movetime",
+        "go searchmoves",
+    ] {
+        let lines = session(&[go, "isready"], "readyok");
+        assert!(
+            lines.contains(&String::from("info string error parsing 'go' command")),
+            "{}: {:?}",
+            go,
+            lines
+        );
+        assert!(
+            !lines.iter().any(|line| line.starts_with("bestmove")),
+            "{}",
+            go
+        );
+    }
+}
+
+#[test]
+fn position_without_a_fen_is_rejected() {
+    let lines = session(
+        &[
+            "position startpos moves e2e4",
+            "position fen",
+            "position",
+            "go depth 1 searchmoves e7e5",
+        ],
+        "bestmove",
+    );
+    assert!(lines.contains(&String::from(
+        "info string error parsing 'position' command at 'fen'"
+    )));
+    assert!(lines.contains(&String::from(
+        "info string error parsing 'position' command at 'position'"
+    )));
+    // still the position after 1. e4
+    assert_eq!(lines.last().unwrap(), "bestmove e7e5");
+}
+
+#[test]
+fn perft_hash_outside_the_hash_option_is_rejected() {
+    let lines = session(&["go perft 2 hash 0", "isready"], "readyok");
+    assert!(
+        lines.contains(&String::from(
+            "info string invalid hash size '0', must be 1 to 512 MB"
+        )),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn chess960_option_changes_castle_notation() {
+    let position = "position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+    let go = "go depth 1 searchmoves e1h1";
+    let lines = session(&[position, go], "bestmove");
+    assert_eq!(lines.last().unwrap(), "bestmove e1g1");
+    let chess960 = "setoption name UCI_Chess960 value true";
+    let lines = session(&[chess960, position, go], "bestmove");
+    assert_eq!(lines.last().unwrap(), "bestmove e1h1");
+    // castles are read king-takes-rook too
+    let moves = "position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1h1 e8d8";
+    let lines = session(
+        &[chess960, moves, "go depth 1 searchmoves f1f8"],
+        "bestmove",
+    );
+    assert_eq!(lines.last().unwrap(), "bestmove f1f8");
+}
@@ -0,0 +1,18 @@
+// Win/draw/loss model output, as reported with UCI_ShowWDL
+
+use kimbo::eval::wdl::wdl;
+use kimbo::position::consts::TOTALPHASE;
+
+#[test]
+fn permille_sums_to_1000() {
+    for phase in [0, 6, 12, 18, TOTALPHASE as i16, 30] {
+        let mut prev_win = 0;
+        for score in (-3000..=3000).step_by(7) {
+            let [win, draw, loss] = wdl(score, phase);
+            assert_eq!(win + draw + loss, 1000, "score {} phase {}", score, phase);
+            assert!(win >= prev_win, "score {} phase {}", score, phase);
+            prev_win = win;
+        }
+        assert_eq!(wdl(0, phase)[0], wdl(0, phase)[2]);
+    }
+}
@@ -0,0 +1,221 @@
+// xboard protocol tests, talking to the kimbo binary over stdin and stdout
+
+mod common;
+
+use std::time::Instant;
+
+fn session(commands: &[&str], until: &str) -> Vec<String> {
+    common::session("xboard", commands, until)
+}
+
+/// the engine's move, from the last line of a session ended by it
+fn engine_move(lines: &[String]) -> &str {
+    let last = lines.last().map(String::as_str).unwrap_or_default();
+    last.strip_prefix("move ")
+        .unwrap_or_else(|| panic!("no move in {:?}", lines))
+}
+
+/// rank of the square the move starts from, '1' to '8'
+fn from_rank(m: &str) -> char {
+    m.chars().nth(1).unwrap()
+}
+
+#[test]
+fn usermove_is_answered() {
+    let lines = session(&["new", "sd 1", "usermove e2e4"], "move ");
+    assert!(matches!(from_rank(engine_move(&lines)), '7' | '8'));
+    // without the usermove prefix too
+    let lines = session(&["new", "sd 1", "e2e4"], "move ");
+    assert!(matches!(from_rank(engine_move(&lines)), '7' | '8'));
+}
+
+#[test]
+fn go_plays_the_side_to_move() {
+    let lines = session(&["new", "sd 1", "go"], "move ");
+    assert!(matches!(from_rank(engine_move(&lines)), '1' | '2'));
+    let lines = session(&["new", "force", "e2e4", "sd 1", "go"], "move ");
+    assert!(matches!(from_rank(engine_move(&lines)), '7' | '8'));
+}
+
+#[test]
+fn force_only_records_moves() {
+    let lines = session(&["new", "force", "e2e4", "e7e5", "ping 1"], "pong");
+    assert_eq!(lines.last().unwrap(), "pong 1");
+    assert!(!lines.iter().any(|line| line.starts_with("move ")));
+    // e7e5 was played, so can't be played again
+    let lines = session(
+        &["new", "force", "e2e4", "e7e5", "usermove e7e5", "ping 1"],
+        "pong",
+    );
+    assert!(lines.contains(&String::from(
+        "Illegal move (no piece of yours there): e7e5"
+    )));
+}
+
+#[test]
+fn errors_use_cecp_messages() {
+    for (command, error) in [
+        ("foo", "Error (unknown command): foo"),
+        ("e2e5", "Error (unknown command): e2e5"),
+        ("level 40 x 0", "Error (invalid argument): level 40 x 0"),
+        ("sd", "Error (invalid argument): sd"),
+        ("usermove e2", "Illegal move (invalid notation): e2"),
+        (
+            "usermove e2e5",
+            "Illegal move (piece can't move there): e2e5",
+        ),
+        (
+            "usermove e7e5",
+            "Illegal move (no piece of yours there): e7e5",
+        ),
+    ] {
+        let lines = session(&["new", "force", command, "ping 1"], "pong");
+        assert!(lines.contains(&String::from(error)), "{:?}", lines);
+    }
+    let lines = session(
+        &[
+            "force",
+            "setboard 4k3/8/8/8/8/8/8/r3K3 w - - 0 1",
+            "usermove e1d1",
+            "usermove e1e2",
+            "ping 1",
+        ],
+        "pong",
+    );
+    assert!(
+        lines.contains(&String::from("Illegal move (in check): e1d1")),
+        "{:?}",
+        lines
+    );
+    assert!(
+        !lines.iter().any(|line| line.contains("e1e2")),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn undo_and_remove() {
+    // undo takes back one ply, remove takes back two, so the move taken
+    // back can be played again
+    let moves = ["new", "force", "e2e4", "e7e5"];
+    for (commands, rank) in [
+        (&["undo", "e7e5"][..], ['1', '2']),
+        (&["remove", "e2e4"][..], ['7', '8']),
+        (&["remove"][..], ['1', '2']),
+    ] {
+        let commands = [&moves[..], commands, &["ping 1"]].concat();
+        let lines = session(&commands, "pong");
+        assert!(
+            !lines.iter().any(|line| line.starts_with("Illegal move")),
+            "{:?}",
+            lines
+        );
+        let commands = [&commands[..], &["sd 1", "go"]].concat();
+        let lines = session(&commands, "move ");
+        assert!(
+            rank.contains(&from_rank(engine_move(&lines))),
+            "{:?}",
+            lines
+        );
+    }
+}
+
+#[test]
+fn setboard_rejects_illegal_positions() {
+    for fen in [
+        "8/8/8/8/8/8/8/8 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+        "not a fen",
+    ] {
+        let setboard = format!("setboard {}", fen);
+        let lines = session(&["new", "force", "e2e4", &setboard, "sd 1", "go"], "move ");
+        assert!(
+            lines.contains(&String::from("tellusererror Illegal position")),
+            "{}: {:?}",
+            fen,
+            lines
+        );
+        // still black to move after 1. e4
+        assert!(matches!(from_rank(engine_move(&lines)), '7' | '8'));
+    }
+    // a legal position is taken
+    let lines = session(
+        &["setboard 4k3/8/8/8/8/8/8/3QK3 b - - 0 1", "sd 1", "go"],
+        "move ",
+    );
+    assert_eq!(from_rank(engine_move(&lines)), '8');
+}
+
+#[test]
+fn sd_limits_depth() {
+    let lines = session(&["new", "post", "sd 3", "go"], "move ");
+    let depths: Vec<i8> = lines
+        .iter()
+        .filter_map(|line| line.split_whitespace().next()?.parse().ok())
+        .collect();
+    assert_eq!(depths.iter().max(), Some(&3), "{:?}", lines);
+    // nothing is posted after nopost
+    let lines = session(&["new", "post", "nopost", "sd 3", "go"], "move ");
+    assert!(
+        !lines.iter().any(|line| line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .parse::<i8>()
+            .is_ok()),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn st_limits_time() {
+    let start = Instant::now();
+    let lines = session(&["new", "st 1", "go"], "move ");
+    engine_move(&lines);
+    let elapsed = start.elapsed().as_millis();
+    assert!((200..5000).contains(&elapsed), "{} ms", elapsed);
+}
+
+#[test]
+fn fullmove_zero_with_session_time_control() {
+    let lines = session(
+        &[
+            "protover 2",
+            "level 40 5 0",
+            "time 100",
+            "otim 100",
+            "setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 0 0",
+            "go",
+        ],
+        "move ",
+    );
+    assert!(
+        lines.last().is_some_and(|line| line.starts_with("move ")),
+        "{:?}",
+        lines
+    );
+}
+
+#[test]
+fn drawn_games_are_not_searched() {
+    let knights = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    let repetition = [&["new", "force"], &knights[..], &knights[..], &["go"]].concat();
+    for (commands, result) in [
+        (
+            &["setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 100 80", "go"][..],
+            "1/2-1/2 {50 move rule}",
+        ),
+        (&repetition[..], "1/2-1/2 {Draw by repetition}"),
+        (
+            &["setboard 4k3/8/8/8/8/8/8/4KN2 w - - 0 1", "go"][..],
+            "1/2-1/2 {Insufficient material}",
+        ),
+    ] {
+        let commands = [commands, &["ping 1"]].concat();
+        let lines = session(&commands, "pong");
+        assert!(lines.contains(&String::from(result)), "{:?}", lines);
+        assert!(!lines.iter().any(|line| line.starts_with("move ")));
+    }
+}